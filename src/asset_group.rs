@@ -0,0 +1,155 @@
+use crate::{Context, Image, Images, LoadState};
+
+/// returns true once an image can be drawn
+pub fn is_loaded(context: &Context, image: Image) -> bool {
+    context.images.load_state(image) == Some(LoadState::Loaded)
+}
+
+/// returns the loading state of an image, `None` if it was unloaded
+pub fn load_state(context: &Context, image: Image) -> Option<LoadState> {
+    context.images.load_state(image)
+}
+
+/// tracks a set of images that are loaded together, e.g. for a loading screen
+#[derive(Debug, Clone, Default)]
+pub struct AssetGroup {
+    images: Vec<Image>,
+}
+
+/// counts of the images in an `AssetGroup` by their state
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub struct AssetGroupStatus {
+    pub loading: usize,
+    pub loaded: usize,
+    /// images that failed to load or were unloaded
+    pub failed: usize,
+}
+
+impl AssetGroupStatus {
+    pub fn total(&self) -> usize {
+        self.loading + self.loaded + self.failed
+    }
+
+    /// the share of finished images between 0 and 1, failed images count as finished
+    pub fn progress(&self) -> f32 {
+        if self.total() == 0 {
+            1.0
+        } else {
+            (self.loaded + self.failed) as f32 / self.total() as f32
+        }
+    }
+
+    /// returns true once no image is loading anymore
+    pub fn is_done(&self) -> bool {
+        self.loading == 0
+    }
+}
+
+impl AssetGroup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// adds an image to the group and returns it
+    pub fn add(&mut self, image: Image) -> Image {
+        self.images.push(image);
+        image
+    }
+
+    pub fn images(&self) -> &[Image] {
+        &self.images
+    }
+
+    pub fn status(&self, context: &Context) -> AssetGroupStatus {
+        self.status_of(&context.images)
+    }
+
+    /// the share of finished images between 0 and 1
+    pub fn progress(&self, context: &Context) -> f32 {
+        self.status(context).progress()
+    }
+
+    /// returns true once no image of the group is loading anymore, some may have failed
+    pub fn is_done(&self, context: &Context) -> bool {
+        self.status(context).is_done()
+    }
+
+    /// returns true once every image of the group has loaded successfully
+    pub fn is_loaded(&self, context: &Context) -> bool {
+        self.status(context).loaded == self.images.len()
+    }
+
+    /// returns the images that failed to load or were unloaded
+    pub fn failed(&self, context: &Context) -> Vec<Image> {
+        self.failed_of(&context.images)
+    }
+
+    fn status_of(&self, images: &Images) -> AssetGroupStatus {
+        let mut status = AssetGroupStatus::default();
+        for image in &self.images {
+            match images.load_state(*image) {
+                Some(LoadState::Loading) => status.loading += 1,
+                Some(LoadState::Loaded) => status.loaded += 1,
+                Some(LoadState::Failed) | None => status.failed += 1,
+            }
+        }
+
+        status
+    }
+
+    fn failed_of(&self, images: &Images) -> Vec<Image> {
+        self.images.iter()
+            .copied()
+            .filter(|image| !matches!(images.load_state(*image), Some(LoadState::Loading) | Some(LoadState::Loaded)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TextureOptions;
+
+    fn image(images: &mut Images, state: LoadState) -> Image {
+        let image = images.insert_texture(1, 1, 1, vec![0; 4], TextureOptions::default());
+        images.set_load_state(image, state);
+        image
+    }
+
+    #[test]
+    fn reports_progress() {
+        let mut images = Images::new();
+        let mut group = AssetGroup::new();
+        let loading = group.add(image(&mut images, LoadState::Loading));
+        group.add(image(&mut images, LoadState::Loaded));
+        let failed = group.add(image(&mut images, LoadState::Failed));
+        group.add(image(&mut images, LoadState::Loaded));
+
+        let status = group.status_of(&images);
+        assert_eq!(status, AssetGroupStatus { loading: 1, loaded: 2, failed: 1 });
+        assert_eq!(status.progress(), 0.75);
+        assert!(!status.is_done());
+        assert_eq!(group.failed_of(&images), vec![failed]);
+
+        images.set_load_state(loading, LoadState::Loaded);
+        assert!(group.status_of(&images).is_done());
+    }
+
+    #[test]
+    fn unloaded_images_count_as_failed() {
+        let mut images = Images::new();
+        let mut group = AssetGroup::new();
+        let image = group.add(image(&mut images, LoadState::Loaded));
+        images.remove(image);
+
+        assert_eq!(group.status_of(&images).failed, 1);
+        assert_eq!(group.failed_of(&images), vec![image]);
+    }
+
+    #[test]
+    fn empty_group_is_done() {
+        let status = AssetGroup::new().status_of(&Images::new());
+        assert!(status.is_done());
+        assert_eq!(status.progress(), 1.0);
+    }
+}
@@ -0,0 +1,136 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use crate::NikoError;
+
+/// the result of reading a file, keyed by its url
+pub type FileResult = (String, Result<Vec<u8>, NikoError>);
+
+/// reads files for `Assets`, reading may finish later than requested
+pub trait FileSource {
+    /// starts reading a file, the result is returned by a later call to `poll`
+    fn request(&mut self, url: &str);
+
+    /// returns the files that finished since the last call
+    fn poll(&mut self) -> Vec<FileResult>;
+}
+
+/// reads files with `window.fetch`
+#[derive(Default)]
+pub struct FetchSource {
+    finished: Rc<RefCell<Vec<FileResult>>>,
+}
+
+impl FetchSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl FileSource for FetchSource {
+    fn request(&mut self, url: &str) {
+        let window = match web_sys::window() {
+            Some(window) => window,
+            None => {
+                self.finished.borrow_mut().push((url.to_string(), Err(NikoError::PlatformError("no window to fetch from".to_string()))));
+                return;
+            },
+        };
+
+        let finished = Rc::clone(&self.finished);
+        let failed = Rc::clone(&self.finished);
+        let response_url = url.to_string();
+        let failed_url = url.to_string();
+
+        let on_response = Closure::wrap(Box::new(move |response: JsValue| {
+            let response: web_sys::Response = response.unchecked_into();
+            if !response.ok() {
+                let error = match response.status() {
+                    404 => NikoError::AssetNotFound { url: response_url.clone() },
+                    status => NikoError::PlatformError(format!("fetching {} failed with status {}", response_url, status)),
+                };
+                finished.borrow_mut().push((response_url.clone(), Err(error)));
+                return;
+            }
+
+            let body = match response.array_buffer() {
+                Ok(body) => body,
+                Err(_) => {
+                    finished.borrow_mut().push((response_url.clone(), Err(NikoError::PlatformError(format!("could not read the body of {}", response_url)))));
+                    return;
+                },
+            };
+
+            let body_finished = Rc::clone(&finished);
+            let body_failed = Rc::clone(&finished);
+            let body_url = response_url.clone();
+            let body_failed_url = response_url.clone();
+            let on_body = Closure::wrap(Box::new(move |buffer: JsValue| {
+                let bytes = js_sys::Uint8Array::new(&buffer).to_vec();
+                body_finished.borrow_mut().push((body_url.clone(), Ok(bytes)));
+            }) as Box<dyn FnMut(JsValue)>);
+            // e.g. when the connection drops while the body is downloading
+            let on_body_error = Closure::wrap(Box::new(move |_error: JsValue| {
+                let error = NikoError::PlatformError(format!("reading the body of {} failed", body_failed_url));
+                body_failed.borrow_mut().push((body_failed_url.clone(), Err(error)));
+            }) as Box<dyn FnMut(JsValue)>);
+            let _ = body.then2(&on_body, &on_body_error);
+            on_body.forget();
+            on_body_error.forget();
+        }) as Box<dyn FnMut(JsValue)>);
+
+        // fetch only rejects on network errors, e.g. when offline or blocked by cors, a missing file is a 404 response
+        let on_error = Closure::wrap(Box::new(move |_error: JsValue| {
+            let error = NikoError::PlatformError(format!("fetching {} failed", failed_url));
+            failed.borrow_mut().push((failed_url.clone(), Err(error)));
+        }) as Box<dyn FnMut(JsValue)>);
+
+        let _ = window.fetch_with_str(url).then2(&on_response, &on_error);
+        on_response.forget();
+        on_error.forget();
+    }
+
+    fn poll(&mut self) -> Vec<FileResult> {
+        std::mem::take(&mut *self.finished.borrow_mut())
+    }
+}
+
+/// serves files from memory, e.g. for tests or data embedded with `include_bytes!`
+#[derive(Debug, Default)]
+pub struct MemorySource {
+    files: HashMap<String, Vec<u8>>,
+    requested: Vec<String>,
+}
+
+impl MemorySource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_file(mut self, url: &str, bytes: impl Into<Vec<u8>>) -> Self {
+        self.insert(url, bytes);
+        self
+    }
+
+    pub fn insert(&mut self, url: &str, bytes: impl Into<Vec<u8>>) {
+        self.files.insert(url.to_string(), bytes.into());
+    }
+}
+
+impl FileSource for MemorySource {
+    fn request(&mut self, url: &str) {
+        self.requested.push(url.to_string());
+    }
+
+    fn poll(&mut self) -> Vec<FileResult> {
+        let files = &self.files;
+        self.requested.drain(..)
+            .map(|url| {
+                let result = files.get(&url).cloned().ok_or_else(|| NikoError::AssetNotFound { url: url.clone() });
+                (url, result)
+            })
+            .collect()
+    }
+}
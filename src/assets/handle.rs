@@ -0,0 +1,48 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use crate::collections::Key as SlotKey;
+
+/// a typed handle to an asset in `Assets`, handles of released assets are stale and no longer find anything
+pub struct Handle<T> {
+    pub(crate) key: SlotKey,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    pub(crate) fn new(key: SlotKey) -> Self {
+        Self {
+            key,
+            marker: PhantomData,
+        }
+    }
+}
+
+// implemented by hand because deriving would require the asset type to implement the traits as well
+impl<T> Copy for Handle<T> {}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.debug_tuple("Handle").field(&self.key).finish()
+    }
+}
@@ -0,0 +1,72 @@
+use std::marker::PhantomData;
+use serde::de::DeserializeOwned;
+use crate::{Error, NikoError};
+
+/// turns the bytes of a file into an asset
+pub trait AssetLoader {
+    type Asset: 'static;
+
+    /// the file extensions handled by this loader without the dot, an empty list handles every file
+    fn extensions(&self) -> &[&'static str] {
+        &[]
+    }
+
+    fn load(&mut self, url: &str, bytes: &[u8]) -> Result<Self::Asset, Error>;
+}
+
+/// loads files as raw bytes
+#[derive(Debug, Default)]
+pub struct BytesLoader;
+
+impl AssetLoader for BytesLoader {
+    type Asset = Vec<u8>;
+
+    fn load(&mut self, _url: &str, bytes: &[u8]) -> Result<Self::Asset, Error> {
+        Ok(bytes.to_vec())
+    }
+}
+
+/// loads files as utf-8 text
+#[derive(Debug, Default)]
+pub struct TextLoader;
+
+impl AssetLoader for TextLoader {
+    type Asset = String;
+
+    fn load(&mut self, url: &str, bytes: &[u8]) -> Result<Self::Asset, Error> {
+        String::from_utf8(bytes.to_vec())
+            .map_err(|error| NikoError::DecodeError { asset: url.to_string(), reason: error.to_string() }.into())
+    }
+}
+
+/// deserializes json files into `T`, has to be registered for every type
+pub struct JsonLoader<T> {
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> JsonLoader<T> {
+    pub fn new() -> Self {
+        Self {
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Default for JsonLoader<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: DeserializeOwned + 'static> AssetLoader for JsonLoader<T> {
+    type Asset = T;
+
+    fn extensions(&self) -> &[&'static str] {
+        &["json"]
+    }
+
+    fn load(&mut self, url: &str, bytes: &[u8]) -> Result<Self::Asset, Error> {
+        serde_json::from_slice(bytes)
+            .map_err(|error| NikoError::DecodeError { asset: url.to_string(), reason: error.to_string() }.into())
+    }
+}
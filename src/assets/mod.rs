@@ -0,0 +1,11 @@
+mod file_source;
+mod handle;
+mod loader;
+mod pack;
+mod store;
+
+pub use file_source::*;
+pub use handle::*;
+pub use loader::*;
+pub use pack::*;
+pub use store::*;
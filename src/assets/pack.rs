@@ -0,0 +1,309 @@
+use std::convert::TryFrom;
+use std::collections::HashMap;
+use crate::{Context, NikoError};
+
+const MAGIC: &[u8; 8] = b"NIKOPAK\0";
+const VERSION: u32 = 1;
+const FLAG_DEFLATE: u8 = 1;
+const COMPRESSION_LEVEL: u8 = 6;
+
+/// starts fetching an asset pack, once it is mounted `load_image` and `load_asset` read matching paths from it
+///
+/// images and assets that are loaded while a pack is still downloading wait for it
+pub fn mount_pack(context: &mut Context, url: &str) {
+    context.assets.mount(url);
+}
+
+/// mounts a pack that is already in memory, e.g. embedded with `include_bytes!`
+pub fn mount_pack_bytes(context: &mut Context, name: &str, bytes: Vec<u8>) -> Result<(), NikoError> {
+    context.assets.mount_pack(name, Pack::parse(bytes)?);
+    Ok(())
+}
+
+/// returns true once the pack fetched from the url has been mounted
+pub fn is_pack_mounted(context: &Context, url: &str) -> bool {
+    context.assets.is_mounted(url)
+}
+
+/// an archive of files by path, the format written by `PackWriter`
+///
+/// the file starts with `NIKOPAK\0`, a little endian u32 version and the number of entries,
+/// followed by the index and the data of all entries
+#[derive(Debug, Clone)]
+pub struct Pack {
+    bytes: Vec<u8>,
+    entries: HashMap<String, PackEntry>,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct PackEntry {
+    offset: usize,
+    stored_size: usize,
+    size: usize,
+    compressed: bool,
+}
+
+impl Pack {
+    /// reads the index of a pack, entries are decompressed when they are read
+    pub fn parse(bytes: Vec<u8>) -> Result<Self, NikoError> {
+        let mut reader = Reader { bytes: &bytes, position: 0 };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(invalid("not an asset pack".to_string()));
+        }
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(invalid(format!("unsupported version {}", version)));
+        }
+
+        let count = reader.u32()?;
+        let mut entries = HashMap::new();
+        for _ in 0..count {
+            let path_length = reader.u16()? as usize;
+            let path = std::str::from_utf8(reader.take(path_length)?)
+                .map_err(|_| invalid("a path is not valid utf-8".to_string()))?
+                .to_string();
+            let flags = reader.u8()?;
+            let entry = PackEntry {
+                offset: reader.size()?,
+                stored_size: reader.size()?,
+                size: reader.size()?,
+                compressed: flags & FLAG_DEFLATE != 0,
+            };
+
+            if entry.offset.checked_add(entry.stored_size).is_none_or(|end| end > bytes.len()) {
+                return Err(invalid(format!("the data of {} is out of bounds", path)));
+            }
+            entries.insert(path, entry);
+        }
+
+        Ok(Self {
+            bytes,
+            entries,
+        })
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.entries.contains_key(normalize(path))
+    }
+
+    /// returns the uncompressed content of a file
+    pub fn read(&self, path: &str) -> Result<Vec<u8>, NikoError> {
+        let entry = self.entries.get(normalize(path))
+            .ok_or_else(|| NikoError::AssetNotFound { url: path.to_string() })?;
+        let stored = &self.bytes[entry.offset..entry.offset + entry.stored_size];
+
+        if !entry.compressed {
+            return Ok(stored.to_vec());
+        }
+
+        let data = miniz_oxide::inflate::decompress_to_vec_with_limit(stored, entry.size)
+            .map_err(|error| invalid(format!("could not decompress {}: {}", path, error)))?;
+        if data.len() != entry.size {
+            return Err(invalid(format!("{} has the wrong size after decompressing", path)));
+        }
+
+        Ok(data)
+    }
+
+    /// iterates over the paths of all files in no particular order
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// builds a `Pack`, e.g. from a build script or a bundling tool
+#[derive(Debug, Default)]
+pub struct PackWriter {
+    files: Vec<(String, Vec<u8>, bool)>,
+}
+
+impl PackWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// adds a file as it is, a file with the same path is replaced
+    pub fn add(&mut self, path: &str, bytes: impl Into<Vec<u8>>) -> &mut Self {
+        self.insert(path, bytes.into(), false)
+    }
+
+    /// adds a deflate compressed file, it is stored as it is if compressing does not make it smaller
+    pub fn add_compressed(&mut self, path: &str, bytes: impl Into<Vec<u8>>) -> &mut Self {
+        self.insert(path, bytes.into(), true)
+    }
+
+    /// writes the index and the data of all files
+    pub fn finish(&self) -> Result<Vec<u8>, NikoError> {
+        let stored: Vec<(&str, Vec<u8>, usize, bool)> = self.files.iter()
+            .map(|(path, bytes, compress)| {
+                let compressed = if *compress { Some(miniz_oxide::deflate::compress_to_vec(bytes, COMPRESSION_LEVEL)) } else { None };
+                match compressed {
+                    Some(compressed) if compressed.len() < bytes.len() => (path.as_str(), compressed, bytes.len(), true),
+                    _ => (path.as_str(), bytes.clone(), bytes.len(), false),
+                }
+            })
+            .collect();
+
+        let count = u32::try_from(stored.len())
+            .map_err(|_| NikoError::InvalidArgument("too many files for an asset pack".to_string()))?;
+        let index_size: usize = stored.iter().map(|(path, ..)| 2 + path.len() + 1 + 8 * 3).sum();
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&count.to_le_bytes());
+
+        let mut offset = bytes.len() + index_size;
+        for (path, data, size, compressed) in &stored {
+            let path_length = u16::try_from(path.len())
+                .map_err(|_| NikoError::InvalidArgument(format!("the path {} is too long for an asset pack", path)))?;
+            bytes.extend_from_slice(&path_length.to_le_bytes());
+            bytes.extend_from_slice(path.as_bytes());
+            bytes.push(if *compressed { FLAG_DEFLATE } else { 0 });
+            bytes.extend_from_slice(&(offset as u64).to_le_bytes());
+            bytes.extend_from_slice(&(data.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(&(*size as u64).to_le_bytes());
+            offset += data.len();
+        }
+
+        for (_, data, ..) in &stored {
+            bytes.extend_from_slice(data);
+        }
+
+        Ok(bytes)
+    }
+
+    fn insert(&mut self, path: &str, bytes: Vec<u8>, compress: bool) -> &mut Self {
+        let path = normalize(path).to_string();
+        self.files.retain(|(existing, ..)| *existing != path);
+        self.files.push((path, bytes, compress));
+        self
+    }
+}
+
+/// removes leading `./` and `/` so `sprites/a.png`, `./sprites/a.png` and `/sprites/a.png` find the same file
+fn normalize(path: &str) -> &str {
+    let mut path = path;
+    loop {
+        if let Some(rest) = path.strip_prefix("./") {
+            path = rest;
+        } else if let Some(rest) = path.strip_prefix('/') {
+            path = rest;
+        } else {
+            return path;
+        }
+    }
+}
+
+fn invalid(reason: String) -> NikoError {
+    NikoError::DecodeError { asset: "asset pack".to_string(), reason }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], NikoError> {
+        let end = self.position.checked_add(length)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| invalid("the index ends early".to_string()))?;
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, NikoError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, NikoError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, NikoError> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn u64(&mut self) -> Result<u64, NikoError> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    /// reads an offset or size, on wasm32 they have to fit into 32 bits
+    fn size(&mut self) -> Result<usize, NikoError> {
+        let size = self.u64()?;
+        usize::try_from(size).map_err(|_| invalid(format!("{} does not fit into memory", size)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pack() -> Vec<u8> {
+        PackWriter::new()
+            .add("sprites/hero.png", vec![1, 2, 3])
+            .add_compressed("data/level.json", "[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]")
+            .add_compressed("data/noise.bin", vec![7])
+            .add("./sprites/hero.png", vec![4, 5])
+            .finish()
+            .unwrap()
+    }
+
+    #[test]
+    fn round_trips() {
+        let pack = Pack::parse(pack()).unwrap();
+
+        assert_eq!(pack.len(), 3);
+        assert_eq!(pack.read("sprites/hero.png").unwrap(), vec![4, 5]);
+        assert_eq!(pack.read("/data/level.json").unwrap(), b"[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]".to_vec());
+        assert_eq!(pack.read("data/noise.bin").unwrap(), vec![7]);
+        assert!(matches!(pack.read("missing.png"), Err(NikoError::AssetNotFound { .. })));
+
+        let mut paths: Vec<&str> = pack.paths().collect();
+        paths.sort_unstable();
+        assert_eq!(paths, vec!["data/level.json", "data/noise.bin", "sprites/hero.png"]);
+    }
+
+    #[test]
+    fn compresses_only_when_smaller() {
+        let pack = Pack::parse(pack()).unwrap();
+        assert!(pack.entries["data/level.json"].compressed);
+        assert!(pack.entries["data/level.json"].stored_size < pack.entries["data/level.json"].size);
+        assert!(!pack.entries["data/noise.bin"].compressed);
+    }
+
+    #[test]
+    fn rejects_broken_packs() {
+        assert!(Pack::parse(b"PK\x03\x04".to_vec()).is_err());
+
+        let bytes = pack();
+        assert!(Pack::parse(bytes[..bytes.len() - 1].to_vec()).is_err());
+        assert!(Pack::parse(bytes[..20].to_vec()).is_err());
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[8] = 2;
+        assert!(Pack::parse(wrong_version).is_err());
+
+        // the offset follows the path and the flags of an entry
+        let path = b"sprites/hero.png";
+        let offset = bytes.windows(path.len()).position(|window| window == path).unwrap() + path.len() + 1;
+        let mut huge_offset = bytes;
+        huge_offset[offset..offset + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(matches!(Pack::parse(huge_offset), Err(NikoError::DecodeError { .. })));
+    }
+}
@@ -0,0 +1,489 @@
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, HashSet};
+use crate::{
+    Context,
+    Error,
+    NikoError,
+    assets::{AssetLoader, BytesLoader, FileResult, FileSource, Handle, Pack, TextLoader},
+    graphics::ImageDataLoader,
+    collections::{Key as SlotKey, SlotList},
+};
+
+/// starts loading an asset, loading a url that is already loaded returns the same handle and adds a reference
+pub fn load_asset<T: 'static>(context: &mut Context, url: &str) -> Result<Handle<T>, Error> {
+    context.assets.load(url)
+}
+
+/// returns the asset once it has loaded
+pub fn get_asset<T: 'static>(context: &Context, handle: Handle<T>) -> Option<&T> {
+    context.assets.get(handle)
+}
+
+/// returns the loading state of an asset, `None` if it was released
+pub fn asset_state<T: 'static>(context: &Context, handle: Handle<T>) -> Option<AssetState> {
+    context.assets.state(handle)
+}
+
+/// registers a loader for its asset type, loaders registered later are preferred
+pub fn register_asset_loader<L: AssetLoader + 'static>(context: &mut Context, loader: L) {
+    context.assets.register_loader(loader);
+}
+
+/// adds a reference to an asset
+pub fn retain_asset<T: 'static>(context: &mut Context, handle: Handle<T>) {
+    context.assets.retain(handle);
+}
+
+/// removes a reference to an asset and frees it once no references are left
+pub fn release_asset<T: 'static>(context: &mut Context, handle: Handle<T>) {
+    context.assets.release(handle);
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum AssetState {
+    Loading,
+    Loaded,
+    Failed,
+}
+
+enum Slot<T> {
+    Loading,
+    Loaded(T),
+    Failed(String),
+}
+
+struct Entry<T> {
+    url: String,
+    references: usize,
+    slot: Slot<T>,
+}
+
+/// all assets and loaders of a single type
+struct Store<T> {
+    entries: SlotList<Entry<T>>,
+    urls: HashMap<String, SlotKey>,
+    loaders: Vec<Box<dyn AssetLoader<Asset = T>>>,
+}
+
+impl<T: 'static> Store<T> {
+    fn new() -> Self {
+        Self {
+            entries: SlotList::new(),
+            urls: HashMap::new(),
+            loaders: Vec::new(),
+        }
+    }
+
+    /// returns the index of the loader for a url, preferring loaders that name its extension
+    fn find_loader(&self, url: &str) -> Option<usize> {
+        let extension = extension(url);
+        let specific = self.loaders.iter().rposition(|loader| loader.extensions().iter().any(|candidate| candidate.eq_ignore_ascii_case(&extension)));
+        specific.or_else(|| self.loaders.iter().rposition(|loader| loader.extensions().is_empty()))
+    }
+}
+
+/// the part of a `Store` that does not depend on its asset type
+trait AnyStore {
+    /// loads the asset waiting for the url, if any
+    fn complete(&mut self, url: &str, result: &Result<Vec<u8>, NikoError>);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: 'static> AnyStore for Store<T> {
+    fn complete(&mut self, url: &str, result: &Result<Vec<u8>, NikoError>) {
+        let key = match self.urls.get(url) {
+            Some(key) => *key,
+            None => return,
+        };
+
+        let slot = match (result, self.find_loader(url)) {
+            (Ok(bytes), Some(loader)) => match self.loaders[loader].load(url, bytes) {
+                Ok(asset) => Slot::Loaded(asset),
+                Err(error) => Slot::Failed(format!("{:#}", error)),
+            },
+            (Ok(_), None) => Slot::Failed(format!("no loader for {}", url)),
+            (Err(error), _) => Slot::Failed(error.to_string()),
+        };
+
+        if let Slot::Failed(error) = &slot {
+            log::error!("could not load {}: {}", url, error);
+        }
+
+        if let Some(entry) = self.entries.get_mut(key) {
+            entry.slot = slot;
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// loads files through a `FileSource` and turns them into typed assets with registered loaders
+pub struct Assets {
+    source: Box<dyn FileSource>,
+    stores: HashMap<TypeId, Box<dyn AnyStore>>,
+    /// urls requested from the source that have not finished yet
+    requested: HashSet<String>,
+    /// mounted packs by url, later packs are searched first
+    packs: Vec<(String, Pack)>,
+    /// packs that are still downloading
+    pending_packs: HashSet<String>,
+    /// urls that wait for the pending packs before they are requested
+    deferred: Vec<String>,
+    /// files read from packs, handed to the loaders on the next update
+    ready: Vec<FileResult>,
+}
+
+impl Assets {
+    /// creates an asset store that loads `Vec<u8>`, `String` and `ImageData` assets out of the box
+    pub fn new(source: Box<dyn FileSource>) -> Self {
+        let mut assets = Self {
+            source,
+            stores: HashMap::new(),
+            requested: HashSet::new(),
+            packs: Vec::new(),
+            pending_packs: HashSet::new(),
+            deferred: Vec::new(),
+            ready: Vec::new(),
+        };
+        assets.register_loader(BytesLoader);
+        assets.register_loader(TextLoader);
+        assets.register_loader(ImageDataLoader);
+        assets
+    }
+
+    pub fn register_loader<L: AssetLoader + 'static>(&mut self, loader: L) {
+        self.store_mut::<L::Asset>().loaders.push(Box::new(loader));
+    }
+
+    pub fn load<T: 'static>(&mut self, url: &str) -> Result<Handle<T>, Error> {
+        let store = self.store_mut::<T>();
+
+        if let Some(key) = store.urls.get(url).copied() {
+            if let Some(entry) = store.entries.get_mut(key) {
+                entry.references += 1;
+                return Ok(Handle::new(key));
+            }
+        }
+
+        if store.find_loader(url).is_none() {
+            return Err(NikoError::InvalidArgument(format!("no loader for {} assets from {}", std::any::type_name::<T>(), url)).into());
+        }
+
+        let key = store.entries.insert(Entry {
+            url: url.to_string(),
+            references: 1,
+            slot: Slot::Loading,
+        });
+        store.urls.insert(url.to_string(), key);
+
+        // several asset types can load the same file, it is only read once
+        if self.requested.insert(url.to_string()) {
+            if self.pending_packs.is_empty() {
+                self.fetch(url);
+            } else {
+                self.deferred.push(url.to_string());
+            }
+        }
+
+        Ok(Handle::new(key))
+    }
+
+    pub fn get<T: 'static>(&self, handle: Handle<T>) -> Option<&T> {
+        match &self.store::<T>()?.entries.get(handle.key)?.slot {
+            Slot::Loaded(asset) => Some(asset),
+            _ => None,
+        }
+    }
+
+    pub fn state<T: 'static>(&self, handle: Handle<T>) -> Option<AssetState> {
+        let state = match self.store::<T>()?.entries.get(handle.key)?.slot {
+            Slot::Loading => AssetState::Loading,
+            Slot::Loaded(_) => AssetState::Loaded,
+            Slot::Failed(_) => AssetState::Failed,
+        };
+
+        Some(state)
+    }
+
+    /// returns why an asset failed to load
+    pub fn error<T: 'static>(&self, handle: Handle<T>) -> Option<&str> {
+        match &self.store::<T>()?.entries.get(handle.key)?.slot {
+            Slot::Failed(error) => Some(error),
+            _ => None,
+        }
+    }
+
+    pub fn retain<T: 'static>(&mut self, handle: Handle<T>) {
+        if let Some(entry) = self.store_mut::<T>().entries.get_mut(handle.key) {
+            entry.references += 1;
+        }
+    }
+
+    /// removes a reference and returns true if the asset was freed
+    pub fn release<T: 'static>(&mut self, handle: Handle<T>) -> bool {
+        let store = self.store_mut::<T>();
+        let entry = match store.entries.get_mut(handle.key) {
+            Some(entry) => entry,
+            None => return false,
+        };
+
+        entry.references -= 1;
+        if entry.references > 0 {
+            return false;
+        }
+
+        if let Some(entry) = store.entries.remove(handle.key) {
+            store.urls.remove(&entry.url);
+        }
+        true
+    }
+
+    /// the number of assets of a type that are loading, loaded or failed
+    pub fn count<T: 'static>(&self) -> usize {
+        self.store::<T>().map(|store| store.entries.len()).unwrap_or(0)
+    }
+
+    /// starts downloading a pack that is mounted once it arrives
+    pub fn mount(&mut self, url: &str) {
+        if self.pending_packs.insert(url.to_string()) {
+            self.source.request(url);
+        }
+    }
+
+    /// mounts a pack right away, its files are preferred over packs mounted before
+    pub fn mount_pack(&mut self, url: &str, pack: Pack) {
+        self.packs.retain(|(mounted, _)| mounted != url);
+        self.packs.push((url.to_string(), pack));
+    }
+
+    pub fn is_mounted(&self, url: &str) -> bool {
+        self.packs.iter().any(|(mounted, _)| mounted == url)
+    }
+
+    /// returns true while a pack is still downloading
+    pub fn has_pending_packs(&self) -> bool {
+        !self.pending_packs.is_empty()
+    }
+
+    /// returns true if a mounted pack contains the file
+    pub fn is_packed(&self, path: &str) -> bool {
+        self.packs.iter().any(|(_, pack)| pack.contains(path))
+    }
+
+    /// reads a file from the mounted packs, `None` if no pack contains it
+    pub fn read_packed(&self, path: &str) -> Option<Result<Vec<u8>, NikoError>> {
+        self.packs.iter()
+            .rev()
+            .find(|(_, pack)| pack.contains(path))
+            .map(|(_, pack)| pack.read(path))
+    }
+
+    /// hands finished files to the loaders, called once per frame
+    pub fn update(&mut self) {
+        let mut results = std::mem::take(&mut self.ready);
+        results.extend(self.source.poll());
+
+        for (url, result) in results {
+            if self.pending_packs.remove(&url) {
+                match result.and_then(Pack::parse) {
+                    Ok(pack) => self.mount_pack(&url, pack),
+                    Err(error) => log::error!("could not mount {}: {}", url, error),
+                }
+                continue;
+            }
+
+            self.requested.remove(&url);
+            for store in self.stores.values_mut() {
+                store.complete(&url, &result);
+            }
+        }
+
+        if self.pending_packs.is_empty() {
+            for url in std::mem::take(&mut self.deferred) {
+                self.fetch(&url);
+            }
+        }
+    }
+
+    /// reads a file from the packs or requests it from the source
+    fn fetch(&mut self, url: &str) {
+        match self.read_packed(url) {
+            Some(result) => self.ready.push((url.to_string(), result)),
+            None => self.source.request(url),
+        }
+    }
+
+    fn store<T: 'static>(&self) -> Option<&Store<T>> {
+        self.stores.get(&TypeId::of::<T>())
+            .and_then(|store| store.as_any().downcast_ref())
+    }
+
+    fn store_mut<T: 'static>(&mut self) -> &mut Store<T> {
+        self.stores.entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Store::<T>::new()))
+            .as_any_mut()
+            .downcast_mut()
+            .expect("asset stores are keyed by their type")
+    }
+}
+
+/// returns the lowercase extension of a url without query or fragment
+fn extension(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let file = path.rsplit('/').next().unwrap_or(path);
+    match file.rfind('.') {
+        Some(index) => file[index + 1..].to_ascii_lowercase(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::{JsonLoader, MemorySource, PackWriter};
+
+    fn assets() -> Assets {
+        let source = MemorySource::new()
+            .with_file("data/level.json", r#"{ "name": "cave", "width": 12 }"#)
+            .with_file("data/notes.txt", "hello")
+            .with_file("data/blob.bin", vec![1, 2, 255]);
+        Assets::new(Box::new(source))
+    }
+
+    #[derive(Debug, serde::Deserialize, PartialEq)]
+    struct Level {
+        name: String,
+        width: u32,
+    }
+
+    #[test]
+    fn loads_after_update() {
+        let mut assets = assets();
+        let bytes: Handle<Vec<u8>> = assets.load("data/blob.bin").unwrap();
+        let text: Handle<String> = assets.load("data/notes.txt").unwrap();
+
+        assert_eq!(assets.state(bytes), Some(AssetState::Loading));
+        assert_eq!(assets.get(bytes), None);
+
+        assets.update();
+        assert_eq!(assets.get(bytes), Some(&vec![1, 2, 255]));
+        assert_eq!(assets.get(text).map(String::as_str), Some("hello"));
+    }
+
+    #[test]
+    fn deserializes_json_with_a_registered_loader() {
+        let mut assets = assets();
+        assert!(assets.load::<Level>("data/level.json").is_err());
+
+        assets.register_loader(JsonLoader::<Level>::new());
+        let level = assets.load::<Level>("data/level.json").unwrap();
+        assets.update();
+
+        assert_eq!(assets.get(level), Some(&Level { name: "cave".to_string(), width: 12 }));
+        assert!(assets.load::<Level>("data/notes.txt").is_err());
+    }
+
+    #[test]
+    fn dedupes_urls_and_counts_references() {
+        let mut assets = assets();
+        let first: Handle<Vec<u8>> = assets.load("data/blob.bin").unwrap();
+        let second: Handle<Vec<u8>> = assets.load("data/blob.bin").unwrap();
+        assert_eq!(first, second);
+        assert_eq!(assets.count::<Vec<u8>>(), 1);
+
+        assert!(!assets.release(first));
+        assets.update();
+        assert!(assets.get(second).is_some());
+
+        assert!(assets.release(second));
+        assert_eq!(assets.count::<Vec<u8>>(), 0);
+        assert_eq!(assets.get(second), None);
+        assert_eq!(assets.state(second), None);
+    }
+
+    #[test]
+    fn reloads_a_released_url_with_a_new_handle() {
+        let mut assets = assets();
+        let old: Handle<String> = assets.load("data/notes.txt").unwrap();
+        assets.release(old);
+
+        let new: Handle<String> = assets.load("data/notes.txt").unwrap();
+        assets.update();
+        assert_ne!(old, new);
+        assert_eq!(assets.get(old), None);
+        assert!(assets.get(new).is_some());
+    }
+
+    #[test]
+    fn reports_failures() {
+        let mut assets = assets();
+        let missing: Handle<Vec<u8>> = assets.load("data/missing.bin").unwrap();
+        let invalid: Handle<String> = assets.load("data/blob.bin").unwrap();
+        assets.update();
+
+        assert_eq!(assets.state(missing), Some(AssetState::Failed));
+        assert!(assets.error(missing).unwrap().contains("data/missing.bin"));
+        assert_eq!(assets.state(invalid), Some(AssetState::Failed));
+        assert!(assets.error(invalid).unwrap().contains("utf-8"));
+    }
+
+    #[test]
+    fn resolves_against_mounted_packs() {
+        let pack = PackWriter::new()
+            .add("data/notes.txt", "packed")
+            .add_compressed("data/extra.txt", "only packed")
+            .finish()
+            .unwrap();
+        let mut assets = Assets::new(Box::new(MemorySource::new()
+            .with_file("assets.pak", pack)
+            .with_file("data/notes.txt", "hello")
+            .with_file("data/blob.bin", vec![1])));
+
+        // loads wait for the pack that is still downloading
+        assets.mount("assets.pak");
+        let notes: Handle<String> = assets.load("data/notes.txt").unwrap();
+        let blob: Handle<Vec<u8>> = assets.load("data/blob.bin").unwrap();
+        assert!(assets.has_pending_packs());
+        assert!(!assets.is_packed("data/notes.txt"));
+        assets.update();
+        assert!(assets.is_mounted("assets.pak"));
+        assert!(!assets.has_pending_packs());
+        assert!(assets.is_packed("./data/extra.txt"));
+        assert!(!assets.is_packed("data/blob.bin"));
+        assert_eq!(assets.state(notes), Some(AssetState::Loading));
+
+        assets.update();
+        assert_eq!(assets.get(notes).map(String::as_str), Some("packed"));
+        assert_eq!(assets.get(blob), Some(&vec![1]));
+
+        let extra: Handle<String> = assets.load("./data/extra.txt").unwrap();
+        assets.update();
+        assert_eq!(assets.get(extra).map(String::as_str), Some("only packed"));
+    }
+
+    #[test]
+    fn failed_packs_release_waiting_loads() {
+        let mut assets = assets();
+        assets.mount("missing.pak");
+        let notes: Handle<String> = assets.load("data/notes.txt").unwrap();
+        assets.update();
+        assets.update();
+
+        assert!(!assets.is_mounted("missing.pak"));
+        assert_eq!(assets.get(notes).map(String::as_str), Some("hello"));
+    }
+
+    #[test]
+    fn extracts_extensions() {
+        assert_eq!(extension("sprites/player.PNG?v=2"), "png");
+        assert_eq!(extension("https://example.com/a.b/level"), "");
+        assert_eq!(extension("level.json#intro"), "json");
+    }
+}
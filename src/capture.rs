@@ -0,0 +1,467 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
+use anyhow::Context as _;
+use wasm_bindgen::{JsCast, closure::Closure};
+use crate::{
+    CANVAS_HEIGHT,
+    CANVAS_WIDTH,
+    Config,
+    Context,
+    Error,
+    ImageData,
+    Key,
+    NikoError,
+    Rectangle,
+    read_pixels,
+};
+
+/// how long a downloaded file stays available to the browser in milliseconds
+const REVOKE_DELAY_MS: i32 = 10_000;
+/// the levels per channel of the palette used for frames with more than 256 colors
+const CUBE_LEVELS: [u32; 3] = [6, 7, 6];
+
+/// saves the canvas as a png download once the current frame has been drawn
+pub fn request_screenshot(context: &mut Context) {
+    context.capture.screenshot_requested = true;
+}
+
+/// saves the recorded seconds as a gif download once the current frame has been drawn
+pub fn save_recording(context: &mut Context) {
+    context.capture.recording_requested = true;
+}
+
+/// starts or stops keeping the last seconds of the canvas for `save_recording`, stopping drops the recorded frames
+///
+/// recording is not free, 20 times a second the whole canvas is read back from the gpu, which waits for drawing
+/// to finish, and reduced to 256 colors on the main thread, so keep it off while measuring performance
+pub fn set_recording(context: &mut Context, enabled: bool) {
+    if enabled && context.capture.recorder.is_none() {
+        context.capture.recorder = Some(GifRecorder::new(context.capture.recording_seconds));
+    } else if !enabled {
+        context.capture.recorder = None;
+    }
+}
+
+pub fn is_recording(context: &Context) -> bool {
+    context.capture.recorder.is_some()
+}
+
+/// encodes rgba pixels as a png file
+pub fn encode_png(image: &ImageData) -> Result<Vec<u8>, Error> {
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut bytes, image.width, image.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().context("could not encode png")?;
+        writer.write_image_data(&image.pixels).context("could not encode png")?;
+    }
+
+    Ok(bytes)
+}
+
+/// hotkeys and requests for screenshots and recordings
+pub(crate) struct Capture {
+    screenshot_key: Option<Key>,
+    recording_key: Option<Key>,
+    recording_seconds: f32,
+    screenshot_requested: bool,
+    recording_requested: bool,
+    recorder: Option<GifRecorder>,
+}
+
+impl Capture {
+    pub(crate) fn new(config: &Config) -> Self {
+        Self {
+            screenshot_key: config.screenshot_key,
+            recording_key: config.recording_key,
+            recording_seconds: config.recording_seconds,
+            screenshot_requested: false,
+            recording_requested: false,
+            recorder: if config.recording { Some(GifRecorder::new(config.recording_seconds)) } else { None },
+        }
+    }
+
+    pub(crate) fn key_pressed(&mut self, key: Key) {
+        if self.screenshot_key == Some(key) {
+            self.screenshot_requested = true;
+        }
+        if self.recording_key == Some(key) {
+            self.recording_requested = true;
+        }
+    }
+
+    /// records the drawn frame and saves requested captures, called after everything was drawn
+    pub(crate) fn end_frame(context: &mut Context) -> Result<(), Error> {
+        let delta = context.time.delta();
+        let wants_frame = context.capture.recorder.as_mut().is_some_and(|recorder| recorder.advance(delta));
+        let screenshot = std::mem::take(&mut context.capture.screenshot_requested);
+        let recording = std::mem::take(&mut context.capture.recording_requested);
+        if !wants_frame && !screenshot && !recording {
+            return Ok(());
+        }
+
+        let frame = read_pixels(context, Rectangle::new(0, 0, CANVAS_WIDTH as i32, CANVAS_HEIGHT as i32))?;
+        let name = format!("niko-{}", js_sys::Date::now() as u64);
+
+        if screenshot {
+            download(&format!("{}.png", name), &encode_png(&frame)?, "image/png")?;
+        }
+
+        match (context.capture.recorder.as_mut(), recording) {
+            (Some(recorder), _) => {
+                if wants_frame {
+                    recorder.push_frame(&frame);
+                }
+                if recording {
+                    download(&format!("{}.gif", name), &recorder.encode()?, "image/gif")?;
+                }
+            },
+            (None, true) => log::warn!("nothing was recorded, turn recording on with `set_recording` or `Config::recording`"),
+            (None, false) => (),
+        }
+
+        Ok(())
+    }
+}
+
+/// a frame of a recording, reduced to at most 256 colors
+struct GifFrame {
+    width: u16,
+    height: u16,
+    /// rgb triples
+    palette: Vec<u8>,
+    indices: Vec<u8>,
+}
+
+/// keeps the frames of the last seconds and encodes them as an animated gif
+pub struct GifRecorder {
+    frames: VecDeque<GifFrame>,
+    seconds: f32,
+    frame_time: f32,
+    since_frame: f32,
+    scale: u32,
+}
+
+impl GifRecorder {
+    /// records 20 frames per second at half of the canvas resolution
+    pub fn new(seconds: f32) -> Self {
+        Self {
+            frames: VecDeque::new(),
+            seconds,
+            frame_time: 1.0 / 20.0,
+            since_frame: f32::INFINITY,
+            scale: 2,
+        }
+    }
+
+    pub fn with_frame_rate(mut self, frames_per_second: f32) -> Self {
+        self.frame_time = 1.0 / frames_per_second.max(1.0);
+        self
+    }
+
+    /// shrinks frames by this factor before they are stored, 1 keeps the full resolution
+    pub fn with_scale(mut self, scale: u32) -> Self {
+        self.scale = scale.max(1);
+        self
+    }
+
+    /// advances the time by `delta` seconds and returns true if the next frame should be recorded
+    pub fn advance(&mut self, delta: f32) -> bool {
+        self.since_frame += delta;
+        if self.since_frame < self.frame_time {
+            return false;
+        }
+
+        // after a long frame the recording continues from now instead of catching up
+        self.since_frame = if self.since_frame >= self.frame_time * 2.0 { 0.0 } else { self.since_frame - self.frame_time };
+        true
+    }
+
+    /// adds a frame and drops frames that are older than the recorded seconds
+    ///
+    /// the frame is downscaled before its colors are reduced, a larger scale makes this cheaper
+    pub fn push_frame(&mut self, frame: &ImageData) {
+        let frame = downscale(frame, self.scale);
+        let (palette, indices) = quantize(&frame.pixels);
+        self.frames.push_back(GifFrame {
+            width: frame.width.min(u16::MAX as u32) as u16,
+            height: frame.height.min(u16::MAX as u32) as u16,
+            palette,
+            indices,
+        });
+
+        let max_frames = ((self.seconds / self.frame_time).round() as usize).max(1);
+        while self.frames.len() > max_frames {
+            self.frames.pop_front();
+        }
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// the length of the recording in seconds
+    pub fn duration(&self) -> f32 {
+        self.frames.len() as f32 * self.frame_time
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// encodes the recorded frames as a looping gif
+    pub fn encode(&self) -> Result<Vec<u8>, Error> {
+        let first = self.frames.front()
+            .ok_or_else(|| NikoError::InvalidArgument("there are no recorded frames to encode".to_string()))?;
+        let delay = (self.frame_time * 100.0).round().max(2.0) as u16;
+
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = gif::Encoder::new(&mut bytes, first.width, first.height, &[]).context("could not encode gif")?;
+            encoder.set_repeat(gif::Repeat::Infinite).context("could not encode gif")?;
+
+            for frame in &self.frames {
+                let gif_frame = gif::Frame {
+                    width: frame.width,
+                    height: frame.height,
+                    delay,
+                    palette: Some(frame.palette.clone()),
+                    buffer: Cow::Borrowed(&frame.indices),
+                    ..gif::Frame::default()
+                };
+                encoder.write_frame(&gif_frame).context("could not encode gif")?;
+            }
+        }
+
+        Ok(bytes)
+    }
+}
+
+/// shrinks an image by averaging blocks of `scale` by `scale` pixels
+fn downscale(image: &ImageData, scale: u32) -> Cow<'_, ImageData> {
+    if scale <= 1 {
+        return Cow::Borrowed(image);
+    }
+
+    let width = (image.width / scale).max(1);
+    let height = (image.height / scale).max(1);
+    let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = [0u32; 4];
+            let mut count = 0;
+            for source_y in y * scale..((y + 1) * scale).min(image.height) {
+                for source_x in x * scale..((x + 1) * scale).min(image.width) {
+                    let index = ((source_y * image.width + source_x) * 4) as usize;
+                    for (total, value) in sum.iter_mut().zip(&image.pixels[index..index + 4]) {
+                        *total += *value as u32;
+                    }
+                    count += 1;
+                }
+            }
+
+            pixels.extend(sum.iter().map(|value| (value / count.max(1)) as u8));
+        }
+    }
+
+    Cow::Owned(ImageData { width, height, pixels })
+}
+
+/// reduces rgba pixels to a palette of rgb triples and an index per pixel, alpha is ignored
+///
+/// frames with at most 256 colors keep their exact colors, others are mapped onto a fixed color cube
+fn quantize(pixels: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let mut palette = Vec::new();
+    let mut lookup = HashMap::new();
+    let mut indices = Vec::with_capacity(pixels.len() / 4);
+    let mut previous: Option<([u8; 3], u8)> = None;
+
+    for pixel in pixels.chunks(4) {
+        let color = [pixel[0], pixel[1], pixel[2]];
+        // neighbouring pixels mostly share their color, which skips most lookups
+        if let Some((previous_color, index)) = previous {
+            if previous_color == color {
+                indices.push(index);
+                continue;
+            }
+        }
+
+        let next = lookup.len();
+        let index = *lookup.entry(color).or_insert(next);
+        if index > 255 {
+            return quantize_to_cube(pixels);
+        }
+        if index == next {
+            palette.extend_from_slice(&color);
+        }
+        indices.push(index as u8);
+        previous = Some((color, index as u8));
+    }
+
+    (palette, indices)
+}
+
+fn quantize_to_cube(pixels: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let [red_levels, green_levels, blue_levels] = CUBE_LEVELS;
+    let level = |value: u8, levels: u32| (value as u32 * (levels - 1) + 127) / 255;
+    let value = |level: u32, levels: u32| (level * 255 / (levels - 1)) as u8;
+
+    let mut palette = Vec::new();
+    for red in 0..red_levels {
+        for green in 0..green_levels {
+            for blue in 0..blue_levels {
+                palette.extend_from_slice(&[value(red, red_levels), value(green, green_levels), value(blue, blue_levels)]);
+            }
+        }
+    }
+
+    let indices = pixels.chunks(4)
+        .map(|pixel| {
+            let red = level(pixel[0], red_levels);
+            let green = level(pixel[1], green_levels);
+            let blue = level(pixel[2], blue_levels);
+            ((red * green_levels + green) * blue_levels + blue) as u8
+        })
+        .collect();
+
+    (palette, indices)
+}
+
+/// makes the browser download bytes as a file
+fn download(file_name: &str, bytes: &[u8], mime_type: &str) -> Result<(), Error> {
+    let platform_error = |_| NikoError::PlatformError(format!("could not download {}", file_name));
+
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes));
+    let options = web_sys::BlobPropertyBag::new();
+    options.set_type(mime_type);
+    let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(&parts, &options).map_err(platform_error)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(platform_error)?;
+
+    let window = web_sys::window()
+        .ok_or_else(|| NikoError::PlatformError("no window found".to_string()))?;
+    let anchor = window.document()
+        .ok_or_else(|| NikoError::PlatformError("no document found".to_string()))?
+        .create_element("a")
+        .map_err(platform_error)?
+        .dyn_into::<web_sys::HtmlAnchorElement>()
+        .map_err(|_| NikoError::PlatformError(format!("could not download {}", file_name)))?;
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+
+    // the browser may only start the download after the click returned, revoking the url right away can cancel it
+    let revoke = Closure::once_into_js(move || {
+        let _ = web_sys::Url::revoke_object_url(&url);
+    });
+    window.set_timeout_with_callback_and_timeout_and_arguments_0(revoke.unchecked_ref(), REVOKE_DELAY_MS)
+        .map_err(platform_error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a frame as it would be read from the canvas, a gradient that changes with `offset`
+    fn frame(width: u32, height: u32, offset: u8) -> ImageData {
+        let pixels = (0..width * height)
+            .flat_map(|index| [(index % width) as u8 * 16, (index / width) as u8 * 16, offset, 255])
+            .collect();
+        ImageData::new(width, height, pixels).unwrap()
+    }
+
+    #[test]
+    fn png_round_trips() {
+        let image = frame(5, 3, 7);
+        assert_eq!(ImageData::decode(&encode_png(&image).unwrap()).unwrap(), image);
+    }
+
+    #[test]
+    fn gif_keeps_the_last_seconds() {
+        let mut recorder = GifRecorder::new(0.5).with_frame_rate(10.0).with_scale(1);
+        for offset in 0..8 {
+            assert!(recorder.advance(0.1));
+            recorder.push_frame(&frame(4, 2, offset * 10));
+        }
+        assert_eq!(recorder.frame_count(), 5);
+        assert!((recorder.duration() - 0.5).abs() < 0.001);
+
+        let bytes = recorder.encode().unwrap();
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = options.read_info(bytes.as_slice()).unwrap();
+
+        let mut blues = Vec::new();
+        while let Some(decoded) = decoder.read_next_frame().unwrap() {
+            assert_eq!((decoded.width, decoded.height, decoded.delay), (4, 2, 10));
+            assert_eq!(&decoded.buffer[..], &frame(4, 2, blues.len() as u8 * 10 + 30).pixels[..]);
+            blues.push(decoded.buffer[2]);
+        }
+        assert_eq!(blues, vec![30, 40, 50, 60, 70]);
+    }
+
+    #[test]
+    fn records_canvas_read_backs() {
+        // rows as `read_pixels` gets them from webgl, bottom row first, with a red band along the top of the canvas
+        let (width, height) = (CANVAS_WIDTH, CANVAS_HEIGHT);
+        let gl_rows: Vec<u8> = (0..width * height)
+            .flat_map(|index| {
+                let (x, y_from_bottom) = (index % width, index / width);
+                if y_from_bottom >= height - 4 {
+                    [255, 0, 0, 255]
+                } else {
+                    [(x % 256) as u8, (y_from_bottom % 256) as u8, 128, 255]
+                }
+            })
+            .collect();
+        let canvas = crate::image_from_gl_rows(width, height, gl_rows).unwrap();
+
+        let mut recorder = GifRecorder::new(1.0);
+        assert!(recorder.advance(0.0));
+        recorder.push_frame(&canvas);
+
+        let bytes = recorder.encode().unwrap();
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = options.read_info(bytes.as_slice()).unwrap();
+        let decoded = decoder.read_next_frame().unwrap().unwrap();
+
+        assert_eq!((decoded.width as u32, decoded.height as u32), (width / 2, height / 2));
+        assert_eq!(&decoded.buffer[..4], &[255, 0, 0, 255]);
+        let bottom_left = ((height / 2 - 1) * width / 2 * 4) as usize;
+        assert_ne!(&decoded.buffer[bottom_left..bottom_left + 4], &[255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn records_at_the_frame_rate() {
+        let mut recorder = GifRecorder::new(1.0).with_frame_rate(10.0);
+        let recorded = (0..60).filter(|_| recorder.advance(1.0 / 60.0)).count();
+        assert!((9..=11).contains(&recorded));
+
+        // a long pause does not record several frames at once
+        assert!(recorder.advance(2.0));
+        assert!(!recorder.advance(0.01));
+        assert!(recorder.encode().is_err());
+    }
+
+    #[test]
+    fn downscales_by_averaging() {
+        let image = ImageData::new(2, 2, vec![0, 0, 0, 255, 100, 0, 0, 255, 0, 200, 0, 255, 0, 0, 40, 255]).unwrap();
+        let small = downscale(&image, 2);
+        assert_eq!((small.width, small.height), (1, 1));
+        assert_eq!(small.pixels, vec![25, 50, 10, 255]);
+    }
+
+    #[test]
+    fn maps_many_colors_onto_the_cube() {
+        let pixels: Vec<u8> = (0..300u32).flat_map(|index| [index as u8, (index / 256) as u8 * 255, 0, 255]).collect();
+        let (palette, indices) = quantize(&pixels);
+        assert_eq!(palette.len(), (6 * 7 * 6) * 3);
+        assert_eq!(indices.len(), 300);
+
+        let white = quantize_to_cube(&[255, 255, 255, 255]).1[0] as usize;
+        assert_eq!(&palette[white * 3..white * 3 + 3], &[255, 255, 255]);
+    }
+}
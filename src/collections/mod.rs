@@ -1,4 +1,3 @@
-#[allow(dead_code)]
 mod slot_list;
 
 pub use slot_list::*;
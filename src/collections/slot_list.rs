@@ -17,6 +17,7 @@ pub struct SlotList<T> {
     items: Vec<Option<T>>,
     generations: Vec<usize>,
     empty: Vec<usize>,
+    len: usize,
 }
 
 impl<T> SlotList<T> {
@@ -25,11 +26,14 @@ impl<T> SlotList<T> {
             items: Vec::new(),
             generations: Vec::new(),
             empty: Vec::new(),
+            len: 0,
         }
     }
 
     /// Inserts the item into this SlotList and returns a Key to it.
     pub fn insert(&mut self, item: T) -> Key {
+        self.len += 1;
+
         if let Some(index) = self.empty.pop() {
             self.items[index] = Some(item);
             self.generations[index] += 1;
@@ -47,50 +51,157 @@ impl<T> SlotList<T> {
 
     /// Removes an item from this SlotList if one was stored for the given key, invalidates the key and returns the item. Otherwise returns None.
     pub fn remove(&mut self, key: Key) -> Option<T> {
-        if key.index > self.items.len() {
-            return None;
-        }
-
-        if self.generations[key.index] != key.generation {
+        if !self.is_valid(key) {
             return None;
         }
 
         self.generations[key.index] += 1;
         self.empty.push(key.index);
+        self.len -= 1;
         self.items[key.index].take()
     }
 
     /// Returns an immutable reference to an item from this SlotList if an item is still stored in it for the given key, otherwise returns None.
     pub fn get(&self, key: Key) -> Option<&T> {
-        if key.index > self.items.len() {
-            return None;
-        }
-
-        if self.generations[key.index] != key.generation {
+        if !self.is_valid(key) {
             return None;
         }
 
-        if let Some(item) = &self.items[key.index] {
-            Some(item)
-        } else {
-            None
-        }
+        self.items[key.index].as_ref()
     }
 
     /// Returns a mutable reference to an item from this SlotList if an item is still stored in it for the given key, otherwise returns None.
     pub fn get_mut(&mut self, key: Key) -> Option<&mut T> {
-        if key.index > self.items.len() {
+        if !self.is_valid(key) {
             return None;
         }
 
-        if self.generations[key.index] != key.generation {
-            return None;
+        self.items[key.index].as_mut()
+    }
+
+    /// Returns true if an item is stored for the given key.
+    pub fn contains(&self, key: Key) -> bool {
+        self.is_valid(key) && self.items[key.index].is_some()
+    }
+
+    /// Returns the number of stored items.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Iterates over all stored items and their keys.
+    pub fn iter(&self) -> impl Iterator<Item = (Key, &T)> {
+        let generations = &self.generations;
+        self.items.iter()
+            .enumerate()
+            .filter_map(move |(index, item)| item.as_ref().map(|item| (Key::new(index, generations[index]), item)))
+    }
+
+    /// Iterates mutably over all stored items and their keys.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Key, &mut T)> {
+        let generations = &self.generations;
+        self.items.iter_mut()
+            .enumerate()
+            .filter_map(move |(index, item)| item.as_mut().map(|item| (Key::new(index, generations[index]), item)))
+    }
+
+    /// Removes all items for which the predicate returns false and invalidates their keys.
+    pub fn retain(&mut self, mut predicate: impl FnMut(Key, &mut T) -> bool) {
+        for index in 0..self.items.len() {
+            let key = Key::new(index, self.generations[index]);
+            let keep = match &mut self.items[index] {
+                Some(item) => predicate(key, item),
+                None => true,
+            };
+
+            if !keep {
+                self.remove(key);
+            }
         }
+    }
 
-        if let Some(item) = &mut self.items[key.index] {
-            Some(item)
-        } else {
-            None
+    /// Removes all items and invalidates all keys.
+    pub fn clear(&mut self) {
+        self.retain(|_, _| false);
+    }
+
+    fn is_valid(&self, key: Key) -> bool {
+        key.index < self.items.len() && self.generations[key.index] == key.generation
+    }
+}
+
+impl<T> Default for SlotList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stale_keys_return_none() {
+        let mut list = SlotList::new();
+        let first = list.insert("first");
+        assert_eq!(list.remove(first), Some("first"));
+
+        let second = list.insert("second");
+        assert_eq!(second.index, first.index);
+        assert_eq!(list.get(first), None);
+        assert_eq!(list.remove(first), None);
+        assert_eq!(list.get(second), Some(&"second"));
+        assert_eq!(list.len(), 1);
+    }
+
+    #[test]
+    fn rejects_keys_past_the_end() {
+        let mut list = SlotList::new();
+        list.insert(1);
+
+        let key = Key::new(1, 0);
+        assert_eq!(list.get(key), None);
+        assert_eq!(list.get_mut(key), None);
+        assert_eq!(list.remove(key), None);
+    }
+
+    #[test]
+    fn iterates_stored_items() {
+        let mut list = SlotList::new();
+        let a = list.insert(1);
+        let b = list.insert(2);
+        let c = list.insert(3);
+        list.remove(b);
+
+        for (_, item) in list.iter_mut() {
+            *item *= 10;
         }
+
+        let items: Vec<(Key, i32)> = list.iter().map(|(key, item)| (key, *item)).collect();
+        assert_eq!(items, vec![(a, 10), (c, 30)]);
+    }
+
+    #[test]
+    fn retain_and_clear_invalidate_keys() {
+        let mut list = SlotList::new();
+        let keys: Vec<Key> = (0..4).map(|item| list.insert(item)).collect();
+
+        list.retain(|_, item| *item % 2 == 0);
+        assert_eq!(list.len(), 2);
+        assert!(list.contains(keys[0]));
+        assert!(!list.contains(keys[1]));
+
+        list.clear();
+        assert!(list.is_empty());
+        assert_eq!(list.get(keys[2]), None);
+        assert_eq!(list.iter().count(), 0);
+
+        let key = list.insert(7);
+        assert_eq!(list.get(key), Some(&7));
+        assert_eq!(list.len(), 1);
     }
 }
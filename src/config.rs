@@ -0,0 +1,49 @@
+use crate::{Key, LevelFilter};
+
+/// settings for the engine, passed to `run_with_config`
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// the key that shows or hides the debug draw layer, None disables toggling by key
+    pub debug_key: Option<Key>,
+    /// shows a graph of frame times and the current frame statistics from the start
+    pub metrics_overlay: bool,
+    /// logs a summary of the frame statistics to the console every few seconds
+    pub metrics_logging: bool,
+    /// collects `profile_scope!` timings from the start, see `export_chrome_trace`
+    pub profiling: bool,
+    /// the level logged for targets without a filter
+    pub log_level: LevelFilter,
+    /// log levels per target prefix, e.g. `("niko::graphics".to_string(), LevelFilter::Debug)`
+    pub log_filters: Vec<(String, LevelFilter)>,
+    /// the key that downloads a png of the canvas, e.g. `Some(Key::F9)`, None by default
+    pub screenshot_key: Option<Key>,
+    /// the key that downloads a gif of the recorded seconds, e.g. `Some(Key::F10)`, None by default
+    pub recording_key: Option<Key>,
+    /// keeps the last seconds of the canvas from the start, see `set_recording` for what it costs
+    pub recording: bool,
+    /// how many seconds a recording keeps
+    pub recording_seconds: f32,
+    /// the websocket url of a dev server that sends changed images and shaders, e.g. `ws://localhost:8081`
+    pub dev_server: Option<String>,
+    /// the seed of `rng(context)`, a fixed seed replays the same random numbers, None seeds it differently on every start
+    pub seed: Option<u64>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            debug_key: Some(Key::F3),
+            metrics_overlay: false,
+            metrics_logging: false,
+            profiling: false,
+            log_level: LevelFilter::Info,
+            log_filters: Vec::new(),
+            screenshot_key: None,
+            recording_key: None,
+            recording: false,
+            recording_seconds: 5.0,
+            dev_server: None,
+            seed: None,
+        }
+    }
+}
@@ -3,38 +3,115 @@ use std::rc::Rc;
 use std::collections::VecDeque;
 use glow::Context as GlowContext;
 use crate::{
+    Assets,
+    Capture,
+    Config,
+    DevChannel,
+    Error,
+    DebugDraw,
     Event,
+    FetchSource,
     Images,
     Input,
+    LocalStorage,
+    LoopState,
     Metrics,
+    NikoError,
+    Rng,
+    StorageBackend,
+    Time,
     graphics::Shader,
+    resources::{EngineShader, ShaderSources},
 };
 
 pub struct Context {
     pub(crate) gl: GlowContext,
     pub(crate) event_queue: Rc<RefCell<VecDeque<Event>>>,
     pub(crate) images: Images,
+    pub(crate) assets: Assets,
     pub(crate) input: Input,
     pub(crate) metrics: Metrics,
+    pub(crate) time: Time,
     pub(crate) sprite_shader: Shader,
+    pub(crate) shape_shader: Shader,
+    pub(crate) shader_sources: ShaderSources,
+    pub(crate) debug: DebugDraw,
+    pub(crate) capture: Capture,
+    pub(crate) dev_channel: Option<Box<dyn DevChannel>>,
+    pub(crate) storage: Box<dyn StorageBackend>,
+    pub(crate) rng: Rng,
+    pub(crate) state: LoopState,
+    pub(crate) context_lost: bool,
 }
 
 impl Context {
     pub(crate) fn new(
         gl: GlowContext,
         event_queue: Rc<RefCell<VecDeque<Event>>>,
-        images: Images,
-        input: Input,
-        metrics: Metrics,
         sprite_shader: Shader,
+        shape_shader: Shader,
+        config: &Config,
     ) -> Self {
         Self {
             gl,
             event_queue,
-            images,
-            input,
-            metrics,
+            images: Images::new(),
+            assets: Assets::new(Box::new(FetchSource::new())),
+            input: Input::new(),
+            metrics: Metrics::new(),
+            time: Time::new(),
             sprite_shader,
+            shape_shader,
+            shader_sources: ShaderSources::default(),
+            debug: DebugDraw::new(config.debug_key),
+            capture: Capture::new(config),
+            dev_channel: None,
+            storage: Box::new(LocalStorage::new()),
+            rng: config.seed.map_or_else(Rng::from_entropy, Rng::new),
+            state: LoopState::Running,
+            context_lost: false,
         }
     }
+
+    /// re-creates shaders and textures after the webgl context was restored
+    pub(crate) fn restore(&mut self) -> Result<(), Error> {
+        let (sprite_shader, shape_shader) = create_shaders(&self.gl, &self.shader_sources)?;
+        self.sprite_shader = sprite_shader;
+        self.shape_shader = shape_shader;
+
+        self.images.restore(&self.gl)
+    }
+
+    /// replaces the source of an engine shader by its file name and rebuilds the shader in place
+    ///
+    /// the old shader and source stay in use if the new source does not compile
+    pub(crate) fn reload_shader(&mut self, name: &str, source: String) -> Result<(), Error> {
+        let mut sources = self.shader_sources.clone();
+        let (shader, vertex, fragment) = match sources.replace(name, source) {
+            Some(EngineShader::SpriteBatch) => (&mut self.sprite_shader, &sources.sprite_batch_vertex, &sources.sprite_batch_fragment),
+            Some(EngineShader::Debug) => (&mut self.shape_shader, &sources.debug_vertex, &sources.debug_fragment),
+            None => return Err(NikoError::InvalidArgument(format!("{} is not an engine shader", name)).into()),
+        };
+
+        shader.reload(&self.gl, vertex, fragment)
+            .map_err(|error| error.context(format!("could not reload {}", name)))?;
+        self.shader_sources = sources;
+
+        Ok(())
+    }
+}
+
+/// returns true while the webgl context is lost, update and draw are not called during that time
+pub fn is_context_lost(context: &Context) -> bool {
+    context.context_lost
+}
+
+/// creates the sprite and the shape shader used by the engine
+pub(crate) fn create_shaders(gl: &GlowContext, sources: &ShaderSources) -> Result<(Shader, Shader), Error> {
+    let sprite_shader = Shader::create(gl, &sources.sprite_batch_vertex, &sources.sprite_batch_fragment)
+        .map_err(|error| error.context("could not create sprite shader"))?;
+    let shape_shader = Shader::create(gl, &sources.debug_vertex, &sources.debug_fragment)
+        .map_err(|error| error.context("could not create shape shader"))?;
+
+    Ok((sprite_shader, shape_shader))
 }
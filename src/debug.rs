@@ -0,0 +1,84 @@
+use crate::{
+    Color,
+    Context,
+    Key,
+    Point,
+    Rectangle,
+    graphics::ShapeBatch,
+};
+
+/// scale of the debug font in pixels per font pixel
+const DEBUG_TEXT_SCALE: i32 = 2;
+
+/// draws the outline of a rectangle on the debug layer
+pub fn debug_rect(context: &mut Context, rectangle: &Rectangle, color: Color) {
+    if let Some(shapes) = context.debug.shapes() {
+        shapes.add_rectangle_outline(rectangle, 1, color);
+    }
+}
+
+/// draws a line on the debug layer
+pub fn debug_line(context: &mut Context, from: Point, to: Point, color: Color) {
+    if let Some(shapes) = context.debug.shapes() {
+        shapes.add_line(from, to, 1, color);
+    }
+}
+
+/// draws text on the debug layer, `position` is the top left corner of the text
+pub fn debug_text(context: &mut Context, position: Point, text: &str, color: Color) {
+    if let Some(shapes) = context.debug.shapes() {
+        shapes.add_debug_text(position, text, DEBUG_TEXT_SCALE, color);
+    }
+}
+
+/// shows or hides the debug layer
+pub fn set_debug_draw(context: &mut Context, enabled: bool) {
+    context.debug.enabled = enabled;
+}
+
+pub fn is_debug_draw_enabled(context: &Context) -> bool {
+    context.debug.enabled
+}
+
+/// an immediate mode layer drawn on top of the game, everything added to it is shown for a single frame
+pub struct DebugDraw {
+    enabled: bool,
+    toggle_key: Option<Key>,
+    shapes: ShapeBatch,
+}
+
+impl DebugDraw {
+    pub fn new(toggle_key: Option<Key>) -> Self {
+        Self {
+            enabled: false,
+            toggle_key,
+            shapes: ShapeBatch::new(),
+        }
+    }
+
+    /// the batch to add shapes to, None while the layer is hidden so no work is wasted
+    fn shapes(&mut self) -> Option<&mut ShapeBatch> {
+        if self.enabled {
+            Some(&mut self.shapes)
+        } else {
+            None
+        }
+    }
+
+    /// toggles the layer if the toggle key was just pressed
+    pub(crate) fn key_pressed(&mut self, key: Key) {
+        if self.toggle_key == Some(key) {
+            self.enabled = !self.enabled;
+        }
+    }
+
+    /// draws and clears everything accumulated during this frame, a failure is only logged and never stops the game
+    pub(crate) fn flush(context: &mut Context) {
+        let shapes = std::mem::take(&mut context.debug.shapes);
+        if context.debug.enabled {
+            if let Err(error) = shapes.draw(context) {
+                log::error!("could not draw the debug layer: {:#}", error);
+            }
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use serde::{Deserialize, Serialize};
+use crate::{Context, NikoError, event};
+
+/// replaces the channel over which the dev server sends changes, e.g. with a `StubChannel` in tests
+pub fn set_dev_channel(context: &mut Context, channel: Box<dyn DevChannel>) {
+    context.dev_channel = Some(channel);
+}
+
+/// a notification from the dev server, sent as json with a `type` field, e.g. `{ "type": "asset_changed", "path": "sprites/hero.png" }`
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DevMessage {
+    /// a file next to the game changed, images loaded from it are downloaded again
+    AssetChanged { path: String },
+    /// the source of an engine shader changed, e.g. `sprite_batch_fragment.glsl`
+    ShaderChanged { name: String, source: String },
+}
+
+impl DevMessage {
+    pub fn parse(text: &str) -> Result<Self, NikoError> {
+        serde_json::from_str(text)
+            .map_err(|error| NikoError::DecodeError { asset: "dev message".to_string(), reason: error.to_string() })
+    }
+}
+
+/// receives messages from the dev server
+pub trait DevChannel {
+    /// returns the messages received since the last call
+    fn poll(&mut self) -> Vec<DevMessage>;
+}
+
+/// receives messages from a dev server over a websocket
+pub struct WebSocketChannel {
+    received: Rc<RefCell<Vec<String>>>,
+    // keeps the connection open
+    _socket: web_sys::WebSocket,
+}
+
+impl WebSocketChannel {
+    pub fn connect(url: &str) -> Result<Self, NikoError> {
+        let socket = web_sys::WebSocket::new(url)
+            .map_err(|_| NikoError::PlatformError(format!("could not connect to the dev server at {}", url)))?;
+        let received = Rc::new(RefCell::new(Vec::new()));
+
+        let received_handle = Rc::clone(&received);
+        let closure = event(&socket, "message", move |event: web_sys::MessageEvent| {
+            if let Some(text) = event.data().as_string() {
+                received_handle.borrow_mut().push(text);
+            }
+        })?;
+        closure.forget();
+
+        Ok(Self {
+            received,
+            _socket: socket,
+        })
+    }
+}
+
+impl DevChannel for WebSocketChannel {
+    fn poll(&mut self) -> Vec<DevMessage> {
+        std::mem::take(&mut *self.received.borrow_mut())
+            .iter()
+            .filter_map(|text| match DevMessage::parse(text) {
+                Ok(message) => Some(message),
+                Err(error) => {
+                    log::warn!("ignoring a message from the dev server: {}", error);
+                    None
+                },
+            })
+            .collect()
+    }
+}
+
+/// a channel that receives what was sent to it, clones share their messages
+#[derive(Debug, Clone, Default)]
+pub struct StubChannel {
+    messages: Rc<RefCell<Vec<DevMessage>>>,
+}
+
+impl StubChannel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn send(&self, message: DevMessage) {
+        self.messages.borrow_mut().push(message);
+    }
+}
+
+impl DevChannel for StubChannel {
+    fn poll(&mut self) -> Vec<DevMessage> {
+        std::mem::take(&mut *self.messages.borrow_mut())
+    }
+}
+
+/// applies the changes the dev server sent since the last frame
+pub(crate) fn update(context: &mut Context) {
+    let messages = match context.dev_channel.as_mut() {
+        Some(channel) => channel.poll(),
+        None => return,
+    };
+
+    for message in messages {
+        match message {
+            DevMessage::AssetChanged { path } => {
+                let reloaded = context.images.reload(&path);
+                if reloaded > 0 {
+                    log::info!("reloading {} image(s) from {}", reloaded, path);
+                }
+            },
+            // a broken shader is only reported, the game keeps running with the old one
+            DevMessage::ShaderChanged { name, source } => match context.reload_shader(&name, source) {
+                Ok(()) => log::info!("reloaded {}", name),
+                Err(error) => log::error!("{:#}", error),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_messages() {
+        assert_eq!(
+            DevMessage::parse(r#"{ "type": "asset_changed", "path": "sprites/hero.png" }"#).unwrap(),
+            DevMessage::AssetChanged { path: "sprites/hero.png".to_string() },
+        );
+        assert_eq!(
+            DevMessage::parse(r#"{ "type": "shader_changed", "name": "debug_vertex.glsl", "source": "void main() {}" }"#).unwrap(),
+            DevMessage::ShaderChanged { name: "debug_vertex.glsl".to_string(), source: "void main() {}".to_string() },
+        );
+        assert!(DevMessage::parse(r#"{ "type": "reload_everything" }"#).is_err());
+    }
+
+    #[test]
+    fn stub_channel_delivers_sent_messages_once() {
+        let stub = StubChannel::new();
+        let mut channel: Box<dyn DevChannel> = Box::new(stub.clone());
+
+        stub.send(DevMessage::AssetChanged { path: "a.png".to_string() });
+        stub.send(DevMessage::AssetChanged { path: "b.png".to_string() });
+        assert_eq!(channel.poll().len(), 2);
+        assert!(channel.poll().is_empty());
+    }
+}
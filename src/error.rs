@@ -1,9 +1,84 @@
 use thiserror::*;
+use wasm_bindgen::JsValue;
+use crate::graphics::{ShaderError, WebGLError};
 
 pub type Error = anyhow::Error;
 
+/// errors reported by niko, games can match on them with `error.downcast_ref::<NikoError>()`
 #[derive(Debug, Error)]
 pub enum NikoError {
     #[error("Platform Error: {0}")]
-    PlatformError(String)
+    PlatformError(String),
+
+    #[error("No canvas with id {0} found")]
+    CanvasNotFound(String),
+
+    #[error("WebGL2 is not available")]
+    WebGlUnavailable,
+
+    #[error("Initialization Error: {0}")]
+    InitializationError(String),
+
+    #[error("Asset not found: {url}")]
+    AssetNotFound { url: String },
+
+    #[error("Could not decode {asset}: {reason}")]
+    DecodeError { asset: String, reason: String },
+
+    #[error("{call} failed: {error}")]
+    GlError { call: String, #[source] error: WebGLError },
+
+    #[error(transparent)]
+    ShaderError(#[from] ShaderError),
+
+    /// also returned when creating a gl object fails, which webgl only does while the context is lost
+    #[error("The WebGL context was lost")]
+    ContextLost,
+
+    #[error("Image is not loaded")]
+    ImageNotLoaded,
+
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+
+    /// the storage refused to keep more data, saving something smaller or deleting old saves may help
+    #[error("Storage quota exceeded while saving {key}")]
+    QuotaExceeded { key: String },
+
+    /// e.g. when localStorage is disabled by the browser settings
+    #[error("Storage is not available: {0}")]
+    StorageUnavailable(String),
+}
+
+impl From<NikoError> for JsValue {
+    fn from(error: NikoError) -> Self {
+        JsValue::from_str(&error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load() -> Result<(), Error> {
+        Err(NikoError::AssetNotFound { url: "player.png".to_string() }.into())
+    }
+
+    #[test]
+    fn matches_through_anyhow() {
+        let error = load().unwrap_err();
+
+        match error.downcast_ref::<NikoError>() {
+            Some(NikoError::AssetNotFound { url }) => assert_eq!(url, "player.png"),
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn keeps_the_gl_error_as_source() {
+        let error = NikoError::GlError { call: "drawElements".to_string(), error: WebGLError::InvalidOperation };
+
+        assert!(error.to_string().starts_with("drawElements failed"));
+        assert!(std::error::Error::source(&error).is_some());
+    }
 }
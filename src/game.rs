@@ -1,7 +1,43 @@
-use crate::{Context, Error};
+use crate::{Context, Error, Event};
+
+/// the part of the game loop an error occurred in
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ErrorStage {
+    Initialize,
+    Update,
+    Draw,
+    /// loading assets and other work the engine does between frames
+    Engine,
+}
+
+/// what the game loop does after an error was reported to `Game::on_error`
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ErrorAction {
+    /// ignores the error and keeps running
+    Continue,
+    /// stops calling `Game::update` until `resume` is called, `Game::paused_update` is called instead
+    Pause,
+    /// shows the error on the canvas and stops the loop
+    ShowError,
+    /// stops the loop
+    Stop,
+}
 
 pub trait Game {
     fn initialize(&mut self, context: &mut Context) -> Result<(), Error>;
     fn update(&mut self, context: &mut Context) -> Result<(), Error>;
     fn draw(&mut self, context: &mut Context) -> Result<(), Error>;
+
+    /// called while the loop is paused instead of `update`, e.g. to resume on a key press
+    fn paused_update(&mut self, _context: &mut Context) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// called for every event after the engine handled it, e.g. `Event::ContextRestored`
+    fn on_event(&mut self, _context: &mut Context, _event: &Event) {}
+
+    /// decides how the loop continues after an error, the error has already been logged
+    fn on_error(&mut self, _context: &mut Context, _error: &Error, _stage: ErrorStage) -> ErrorAction {
+        ErrorAction::ShowError
+    }
 }
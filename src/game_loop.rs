@@ -0,0 +1,138 @@
+use crate::{
+    CANVAS_HEIGHT,
+    CANVAS_WIDTH,
+    Color,
+    Context,
+    Error,
+    ErrorAction,
+    ErrorStage,
+    Game,
+    Point,
+    Rectangle,
+    graphics::{ShapeBatch, debug_font},
+};
+
+/// scale of the debug font on the error screen
+const ERROR_TEXT_SCALE: i32 = 2;
+const ERROR_MARGIN: i32 = 32;
+
+/// stops calling `Game::update` until `resume` is called
+pub fn pause(context: &mut Context) {
+    if context.state == LoopState::Running {
+        context.state = LoopState::Paused;
+    }
+}
+
+pub fn resume(context: &mut Context) {
+    if context.state == LoopState::Paused {
+        context.state = LoopState::Running;
+    }
+}
+
+pub fn is_paused(context: &Context) -> bool {
+    context.state == LoopState::Paused
+}
+
+/// returns true if the game should be updated and drawn this frame
+pub(crate) fn is_running(context: &Context) -> bool {
+    context.state != LoopState::Stopped && !context.context_lost
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum LoopState {
+    Running,
+    Paused,
+    Stopped,
+}
+
+impl LoopState {
+    /// returns the state after the game chose how to handle an error
+    pub(crate) fn after_error(self, action: ErrorAction) -> Self {
+        match (self, action) {
+            (LoopState::Stopped, _) => LoopState::Stopped,
+            (state, ErrorAction::Continue) => state,
+            (_, ErrorAction::Pause) => LoopState::Paused,
+            (_, ErrorAction::ShowError) | (_, ErrorAction::Stop) => LoopState::Stopped,
+        }
+    }
+}
+
+/// logs an error, lets the game decide what to do with it and applies that decision
+pub(crate) fn handle_error(game: &mut dyn Game, context: &mut Context, error: Error, stage: ErrorStage) {
+    log::error!("error during {:?}: {:#}", stage, error);
+
+    let action = game.on_error(context, &error, stage);
+    context.state = context.state.after_error(action);
+
+    if action == ErrorAction::ShowError {
+        if let Err(error) = draw_error_screen(context, &format!("{:#}", error)) {
+            log::error!("could not draw the error screen: {:#}", error);
+        }
+    }
+}
+
+fn draw_error_screen(context: &mut Context, message: &str) -> Result<(), Error> {
+    crate::clear(context, Color::rgb(90, 20, 20));
+    error_screen(message).draw(context)
+}
+
+fn error_screen(message: &str) -> ShapeBatch {
+    let columns = ((CANVAS_WIDTH as i32 - 2 * ERROR_MARGIN) / (debug_font::GLYPH_ADVANCE * ERROR_TEXT_SCALE)) as usize;
+    let text = format!("error\n\n{}", wrap(message, columns));
+
+    let mut shapes = ShapeBatch::new();
+    shapes.add_rectangle(&Rectangle::new(0, CANVAS_HEIGHT as i32 - 8, CANVAS_WIDTH as i32, 8), Color::rgb(230, 60, 60));
+    shapes.add_debug_text(Point::new(ERROR_MARGIN, CANVAS_HEIGHT as i32 - ERROR_MARGIN), &text, ERROR_TEXT_SCALE, Color::rgb(255, 255, 255));
+    shapes
+}
+
+/// breaks lines that are longer than the given number of characters, preferably at spaces
+fn wrap(text: &str, columns: usize) -> String {
+    let columns = columns.max(1);
+    let mut lines = Vec::new();
+
+    for line in text.lines() {
+        let mut current = String::new();
+        for word in line.split(' ') {
+            let length = current.chars().count();
+            if length > 0 && length + 1 + word.chars().count() > columns {
+                lines.push(std::mem::take(&mut current));
+            }
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(word);
+
+            while current.chars().count() > columns {
+                let rest: String = current.chars().skip(columns).collect();
+                lines.push(current.chars().take(columns).collect());
+                current = rest;
+            }
+        }
+        lines.push(current);
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_error_actions() {
+        assert_eq!(LoopState::Running.after_error(ErrorAction::Continue), LoopState::Running);
+        assert_eq!(LoopState::Paused.after_error(ErrorAction::Continue), LoopState::Paused);
+        assert_eq!(LoopState::Running.after_error(ErrorAction::Pause), LoopState::Paused);
+        assert_eq!(LoopState::Running.after_error(ErrorAction::ShowError), LoopState::Stopped);
+        assert_eq!(LoopState::Paused.after_error(ErrorAction::Stop), LoopState::Stopped);
+        assert_eq!(LoopState::Stopped.after_error(ErrorAction::Pause), LoopState::Stopped);
+    }
+
+    #[test]
+    fn wraps_long_lines() {
+        assert_eq!(wrap("could not load image", 10), "could not\nload image");
+        assert_eq!(wrap("abcdefghij", 4), "abcd\nefgh\nij");
+        assert_eq!(wrap("first\nsecond line", 6), "first\nsecond\nline");
+    }
+}
@@ -0,0 +1,339 @@
+use crate::graphics::{Sprite, SpriteSheet, SpriteSheetClip, ClipDirection};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum PlaybackMode {
+    /// plays all frames a single time and stops on the last one
+    Once,
+    /// starts over at the first frame after the last one
+    Loop,
+    /// plays forward, then backward, then forward again
+    PingPong,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum AnimationEvent {
+    /// the player switched to the frame with the given index
+    FrameChanged(usize),
+    /// a looping or ping-pong animation started its next cycle
+    Looped,
+    /// an animation played with `PlaybackMode::Once` reached its end
+    Completed,
+}
+
+#[derive(Debug, Clone)]
+pub struct AnimationFrame {
+    pub sprite: Sprite,
+    /// how long this frame is shown in seconds
+    pub duration: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Animation {
+    frames: Vec<AnimationFrame>,
+    mode: PlaybackMode,
+}
+
+impl Animation {
+    pub fn new(frames: Vec<AnimationFrame>, mode: PlaybackMode) -> Self {
+        Self {
+            frames,
+            mode,
+        }
+    }
+
+    /// creates an animation showing every sprite for the same duration in seconds
+    pub fn from_sprites(sprites: &[Sprite], frame_duration: f32, mode: PlaybackMode) -> Self {
+        let frames = sprites.iter()
+            .map(|sprite| AnimationFrame { sprite: sprite.clone(), duration: frame_duration })
+            .collect();
+
+        Self::new(frames, mode)
+    }
+
+    /// creates a looping animation from a clip of a sprite sheet, using the frame durations of the sheet
+    pub fn from_clip(sheet: &SpriteSheet, clip: &SpriteSheetClip) -> Self {
+        let mut frames: Vec<AnimationFrame> = clip.frames.iter().zip(clip.durations.iter())
+            .map(|(index, duration)| AnimationFrame {
+                sprite: sheet.sprites()[*index].clone(),
+                duration: *duration as f32 / 1000.0,
+            })
+            .collect();
+
+        let mode = match clip.direction {
+            ClipDirection::Forward => PlaybackMode::Loop,
+            ClipDirection::Reverse => {
+                frames.reverse();
+                PlaybackMode::Loop
+            },
+            ClipDirection::PingPong => PlaybackMode::PingPong,
+            // bouncing over the reversed frames starts at the last frame and heads backward
+            ClipDirection::PingPongReverse => {
+                frames.reverse();
+                PlaybackMode::PingPong
+            },
+        };
+
+        Self::new(frames, mode)
+    }
+
+    pub fn frames(&self) -> &[AnimationFrame] {
+        &self.frames
+    }
+
+    pub fn mode(&self) -> PlaybackMode {
+        self.mode
+    }
+
+    /// returns a copy of this animation using another playback mode
+    pub fn with_mode(self, mode: PlaybackMode) -> Self {
+        Self {
+            mode,
+            ..self
+        }
+    }
+
+    /// the time in seconds it takes to show every frame once
+    pub fn duration(&self) -> f32 {
+        self.frames.iter().map(|frame| frame.duration).sum()
+    }
+}
+
+/// plays an animation, advanced by the frame delta passed to `update`
+#[derive(Debug, Clone)]
+pub struct AnimationPlayer {
+    animation: Animation,
+    frame: usize,
+    elapsed: f32,
+    speed: f32,
+    forward: bool,
+    playing: bool,
+    events: Vec<AnimationEvent>,
+}
+
+impl AnimationPlayer {
+    pub fn new(animation: Animation) -> Self {
+        Self {
+            animation,
+            frame: 0,
+            elapsed: 0.0,
+            speed: 1.0,
+            forward: true,
+            playing: true,
+            events: Vec::new(),
+        }
+    }
+
+    /// switches to another animation and starts it from the first frame
+    pub fn play(&mut self, animation: Animation) {
+        self.animation = animation;
+        self.restart();
+    }
+
+    pub fn restart(&mut self) {
+        self.frame = 0;
+        self.elapsed = 0.0;
+        self.forward = true;
+        self.playing = true;
+        self.events.clear();
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn resume(&mut self) {
+        self.playing = true;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// sets the playback speed multiplier, negative values are treated as 0
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.max(0.0);
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn animation(&self) -> &Animation {
+        &self.animation
+    }
+
+    /// the index of the frame currently shown
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    /// the sprite of the frame currently shown, None if the animation has no frames
+    pub fn sprite(&self) -> Option<&Sprite> {
+        self.animation.frames.get(self.frame).map(|frame| &frame.sprite)
+    }
+
+    /// the events that happened during the last call to `update`
+    pub fn events(&self) -> &[AnimationEvent] {
+        &self.events
+    }
+
+    /// advances the animation by `delta` seconds and returns what happened meanwhile
+    pub fn update(&mut self, delta: f32) -> &[AnimationEvent] {
+        self.events.clear();
+
+        if !self.playing || self.animation.duration() <= 0.0 {
+            return &self.events;
+        }
+
+        self.elapsed += delta * self.speed;
+        // after a whole cycle the remaining time is dropped, huge deltas would otherwise step through every frame
+        let max_steps = self.animation.frames.len() * 2;
+        let mut steps = 0;
+        while self.playing && self.elapsed >= self.animation.frames[self.frame].duration {
+            if steps == max_steps {
+                self.elapsed %= self.animation.frames[self.frame].duration.max(f32::EPSILON);
+                break;
+            }
+
+            self.elapsed -= self.animation.frames[self.frame].duration;
+            self.advance();
+            steps += 1;
+        }
+
+        &self.events
+    }
+
+    fn advance(&mut self) {
+        let last = self.animation.frames.len() - 1;
+        let previous = self.frame;
+        let mut looped = false;
+        let mut completed = false;
+
+        match self.animation.mode {
+            PlaybackMode::Once => {
+                if self.frame == last {
+                    self.playing = false;
+                    self.elapsed = 0.0;
+                    completed = true;
+                } else {
+                    self.frame += 1;
+                }
+            },
+            PlaybackMode::Loop => {
+                if self.frame == last {
+                    self.frame = 0;
+                    looped = true;
+                } else {
+                    self.frame += 1;
+                }
+            },
+            PlaybackMode::PingPong => {
+                if self.forward {
+                    self.frame = (self.frame + 1).min(last);
+                    self.forward = self.frame != last;
+                } else {
+                    self.frame = self.frame.saturating_sub(1);
+                    self.forward = self.frame == 0;
+                    looped = self.forward;
+                }
+            },
+        }
+
+        if self.frame != previous {
+            self.events.push(AnimationEvent::FrameChanged(self.frame));
+        }
+
+        if looped {
+            self.events.push(AnimationEvent::Looped);
+        }
+
+        if completed {
+            self.events.push(AnimationEvent::Completed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Image, Rectangle};
+
+    fn animation(frames: usize, mode: PlaybackMode) -> Animation {
+        let sprites: Vec<Sprite> = (0..frames)
+            .map(|index| Sprite::new(Image::detached(), Rectangle::new(index as i32 * 8, 0, 8, 8)))
+            .collect();
+
+        Animation::from_sprites(&sprites, 0.1, mode)
+    }
+
+    fn frames_over(player: &mut AnimationPlayer, steps: usize, delta: f32) -> Vec<usize> {
+        (0..steps).map(|_| {
+            player.update(delta);
+            player.frame()
+        }).collect()
+    }
+
+    #[test]
+    fn loops() {
+        let mut player = AnimationPlayer::new(animation(3, PlaybackMode::Loop));
+        assert_eq!(frames_over(&mut player, 6, 0.1), vec![1, 2, 0, 1, 2, 0]);
+    }
+
+    #[test]
+    fn ping_pongs() {
+        let mut player = AnimationPlayer::new(animation(3, PlaybackMode::PingPong));
+        assert_eq!(frames_over(&mut player, 6, 0.1), vec![1, 2, 1, 0, 1, 2]);
+    }
+
+    #[test]
+    fn plays_once() {
+        let mut player = AnimationPlayer::new(animation(3, PlaybackMode::Once));
+        assert_eq!(frames_over(&mut player, 2, 0.1), vec![1, 2]);
+        assert_eq!(player.update(0.1), &[AnimationEvent::Completed]);
+        assert!(!player.is_playing());
+        assert_eq!(frames_over(&mut player, 2, 0.1), vec![2, 2]);
+    }
+
+    #[test]
+    fn reports_events() {
+        let mut player = AnimationPlayer::new(animation(2, PlaybackMode::Loop));
+        assert!(player.update(0.05).is_empty());
+        assert_eq!(player.update(0.05), &[AnimationEvent::FrameChanged(1)]);
+        assert_eq!(player.update(0.1), &[AnimationEvent::FrameChanged(0), AnimationEvent::Looped]);
+    }
+
+    #[test]
+    fn skips_frames_on_large_delta() {
+        let mut player = AnimationPlayer::new(animation(4, PlaybackMode::Loop));
+        let events = player.update(0.25).to_vec();
+        assert_eq!(player.frame(), 2);
+        assert_eq!(events, vec![AnimationEvent::FrameChanged(1), AnimationEvent::FrameChanged(2)]);
+    }
+
+    #[test]
+    fn catches_up_at_most_one_cycle() {
+        let mut player = AnimationPlayer::new(animation(3, PlaybackMode::Loop));
+        let events = player.update(1_000_000.0).to_vec();
+        assert_eq!(events.iter().filter(|event| **event == AnimationEvent::Looped).count(), 2);
+        assert!(player.elapsed < 0.1);
+    }
+
+    #[test]
+    fn respects_speed() {
+        let mut player = AnimationPlayer::new(animation(3, PlaybackMode::Loop));
+        player.set_speed(2.0);
+        assert_eq!(frames_over(&mut player, 3, 0.05), vec![1, 2, 0]);
+
+        player.set_speed(0.0);
+        assert_eq!(frames_over(&mut player, 3, 0.05), vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn pauses() {
+        let mut player = AnimationPlayer::new(animation(3, PlaybackMode::Loop));
+        player.pause();
+        assert_eq!(frames_over(&mut player, 2, 0.1), vec![0, 0]);
+        player.resume();
+        assert_eq!(frames_over(&mut player, 2, 0.1), vec![1, 2]);
+    }
+}
@@ -0,0 +1,364 @@
+use crate::{
+    Error,
+    NikoError,
+    Context,
+    Image,
+    Point,
+    Rectangle,
+    graphics::{
+        Sprite,
+        TextureOptions,
+    },
+};
+
+const INITIAL_ATLAS_SIZE: u32 = 256;
+const MAX_ATLAS_SIZE: u32 = 4096;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct SkylineSegment {
+    x: u32,
+    y: u32,
+    width: u32,
+}
+
+/// packs rectangles into a fixed size area using the skyline bottom-left heuristic
+#[derive(Debug, Clone)]
+pub struct SkylinePacker {
+    width: u32,
+    height: u32,
+    skyline: Vec<SkylineSegment>,
+}
+
+impl SkylinePacker {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            skyline: vec![SkylineSegment { x: 0, y: 0, width }],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// finds a free spot for a rectangle of the given size and reserves it, returns None if there is no space left
+    pub fn pack(&mut self, width: u32, height: u32) -> Option<Point> {
+        if width == 0 || height == 0 {
+            return Some(Point::new(0, 0));
+        }
+
+        let mut best: Option<(usize, u32, u32)> = None;
+        for index in 0..self.skyline.len() {
+            if let Some(y) = self.fits(index, width, height) {
+                let segment_width = self.skyline[index].width;
+                let is_better = match best {
+                    None => true,
+                    Some((_, best_y, best_width)) => y < best_y || (y == best_y && segment_width < best_width),
+                };
+
+                if is_better {
+                    best = Some((index, y, segment_width));
+                }
+            }
+        }
+
+        let (index, y, _) = best?;
+        let x = self.skyline[index].x;
+        self.insert_segment(index, x, y + height, width);
+
+        Some(Point::new(x as i32, y as i32))
+    }
+
+    /// returns the y position a rectangle starting at the given segment would rest on, if it fits
+    fn fits(&self, index: usize, width: u32, height: u32) -> Option<u32> {
+        let x = self.skyline[index].x;
+        if x + width > self.width {
+            return None;
+        }
+
+        let mut y = 0;
+        let mut remaining = width as i64;
+        let mut current = index;
+        while remaining > 0 {
+            let segment = self.skyline.get(current)?;
+            y = y.max(segment.y);
+            if y + height > self.height {
+                return None;
+            }
+
+            remaining -= segment.width as i64;
+            current += 1;
+        }
+
+        Some(y)
+    }
+
+    fn insert_segment(&mut self, index: usize, x: u32, y: u32, width: u32) {
+        self.skyline.insert(index, SkylineSegment { x, y, width });
+
+        // shrink or remove the segments now covered by the new one
+        let right = x + width;
+        let next = index + 1;
+        while next < self.skyline.len() {
+            let segment = self.skyline[next];
+            if segment.x >= right {
+                break;
+            }
+
+            let segment_right = segment.x + segment.width;
+            if segment_right <= right {
+                self.skyline.remove(next);
+            } else {
+                self.skyline[next].x = right;
+                self.skyline[next].width = segment_right - right;
+                break;
+            }
+        }
+
+        // merge neighbours on the same height
+        let mut current = 0;
+        while current + 1 < self.skyline.len() {
+            if self.skyline[current].y == self.skyline[current + 1].y {
+                self.skyline[current].width += self.skyline[current + 1].width;
+                self.skyline.remove(current + 1);
+            } else {
+                current += 1;
+            }
+        }
+    }
+}
+
+/// copies a rgba region of `source` into `target` at the given position, clipping at the target bounds
+pub(crate) fn blit_rgba(source: &[u8], source_width: u32, source_height: u32, target: &mut [u8], target_width: u32, target_height: u32, position: Point) {
+    for row in 0..source_height {
+        let target_y = position.y + row as i32;
+        if target_y < 0 || target_y >= target_height as i32 {
+            continue;
+        }
+
+        let start_x = position.x.max(0);
+        let end_x = (position.x + source_width as i32).min(target_width as i32);
+        if start_x >= end_x {
+            continue;
+        }
+
+        let source_start = ((row * source_width) as i32 + (start_x - position.x)) as usize * 4;
+        let target_start = (target_y * target_width as i32 + start_x) as usize * 4;
+        let length = (end_x - start_x) as usize * 4;
+
+        target[target_start..target_start + length].copy_from_slice(&source[source_start..source_start + length]);
+    }
+}
+
+enum AtlasSource {
+    Image(Image),
+    Pixels { width: u32, height: u32, data: Vec<u8> },
+}
+
+/// merges many loaded images and raw rgba data into a single texture so sprites can be batched together
+pub struct AtlasBuilder {
+    sources: Vec<AtlasSource>,
+    padding: u32,
+    options: TextureOptions,
+}
+
+impl AtlasBuilder {
+    pub fn new() -> Self {
+        Self {
+            sources: Vec::new(),
+            padding: 1,
+            options: TextureOptions::default(),
+        }
+    }
+
+    /// sets the amount of empty pixels between packed images, defaults to 1
+    pub fn with_padding(self, padding: u32) -> Self {
+        Self {
+            padding,
+            ..self
+        }
+    }
+
+    /// sets the sampling options of the resulting atlas texture
+    pub fn with_options(self, options: TextureOptions) -> Self {
+        Self {
+            options,
+            ..self
+        }
+    }
+
+    /// adds an already loaded image and returns the index of its sprite in the build result
+    pub fn add_image(&mut self, image: Image) -> usize {
+        self.sources.push(AtlasSource::Image(image));
+        self.sources.len() - 1
+    }
+
+    /// adds raw rgba pixel data and returns the index of its sprite in the build result
+    pub fn add_pixels(&mut self, width: u32, height: u32, data: Vec<u8>) -> Result<usize, Error> {
+        if data.len() != (width * height * 4) as usize {
+            return Err(NikoError::InvalidArgument(format!("expected {} bytes of rgba data for a {}x{} image, got {}", width * height * 4, width, height, data.len())).into());
+        }
+
+        self.sources.push(AtlasSource::Pixels { width, height, data });
+        Ok(self.sources.len() - 1)
+    }
+
+    /// packs all added images into one texture, returns one sprite per added image in insertion order
+    pub fn build(self, context: &mut Context) -> Result<Vec<Sprite>, Error> {
+        let mut pixels = Vec::with_capacity(self.sources.len());
+        for source in self.sources {
+            match source {
+                AtlasSource::Image(image) => {
+                    let (width, height) = context.images.find_size(image)
+                        .ok_or(NikoError::ImageNotLoaded)?;
+                    let data = context.images.read_pixels(image, &context.gl)?;
+                    pixels.push((width, height, data));
+                },
+                AtlasSource::Pixels { width, height, data } => {
+                    pixels.push((width, height, data));
+                },
+            }
+        }
+
+        let sizes: Vec<(u32, u32)> = pixels.iter().map(|(width, height, _)| (*width, *height)).collect();
+        let (atlas_width, atlas_height, positions) = pack_all(&sizes, self.padding)
+            .ok_or_else(|| NikoError::InvalidArgument(format!("images do not fit into a {}x{} atlas", MAX_ATLAS_SIZE, MAX_ATLAS_SIZE)))?;
+
+        let mut data = vec![0; (atlas_width * atlas_height * 4) as usize];
+        for ((width, height, source), position) in pixels.iter().zip(positions.iter()) {
+            blit_rgba(source, *width, *height, &mut data, atlas_width, atlas_height, *position);
+        }
+
+        let image = context.images.create_image_from_pixels(&context.gl, atlas_width, atlas_height, data, self.options)?;
+
+        let sprites = sizes.iter().zip(positions.iter())
+            .map(|((width, height), position)| Sprite::new(image, Rectangle::new(position.x, position.y, *width as i32, *height as i32)))
+            .collect();
+
+        Ok(sprites)
+    }
+}
+
+impl Default for AtlasBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// packs all sizes, largest first, into the smallest power of two area that fits them
+fn pack_all(sizes: &[(u32, u32)], padding: u32) -> Option<(u32, u32, Vec<Point>)> {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by(|a, b| {
+        let (a_width, a_height) = sizes[*a];
+        let (b_width, b_height) = sizes[*b];
+        b_height.cmp(&a_height).then(b_width.cmp(&a_width))
+    });
+
+    let mut width = INITIAL_ATLAS_SIZE;
+    let mut height = INITIAL_ATLAS_SIZE;
+    loop {
+        let mut packer = SkylinePacker::new(width, height);
+        let mut positions = vec![Point::new(0, 0); sizes.len()];
+        let mut success = true;
+        for index in &order {
+            let (item_width, item_height) = sizes[*index];
+            match packer.pack(item_width + padding, item_height + padding) {
+                Some(position) => positions[*index] = position,
+                None => {
+                    success = false;
+                    break;
+                },
+            }
+        }
+
+        if success {
+            return Some((width, height, positions));
+        }
+
+        if width == MAX_ATLAS_SIZE && height == MAX_ATLAS_SIZE {
+            return None;
+        }
+
+        if width <= height {
+            width *= 2;
+        } else {
+            height *= 2;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pack_checked(packer: &mut SkylinePacker, sizes: &[(u32, u32)]) -> Vec<Rectangle> {
+        let mut placed: Vec<Rectangle> = Vec::new();
+        for (width, height) in sizes {
+            let position = packer.pack(*width, *height).expect("rectangle should fit");
+            let rectangle = Rectangle::new(position.x, position.y, *width as i32, *height as i32);
+
+            let bounds = Rectangle::new(0, 0, packer.width() as i32, packer.height() as i32);
+            assert!(bounds.contains_rectangle(&rectangle), "{:?} is out of bounds", rectangle);
+            for other in &placed {
+                assert!(!rectangle.overlaps_rectangle(other), "{:?} overlaps {:?}", rectangle, other);
+            }
+
+            placed.push(rectangle);
+        }
+
+        placed
+    }
+
+    #[test]
+    fn packs_without_overlap() {
+        let mut packer = SkylinePacker::new(128, 128);
+        let sizes: Vec<(u32, u32)> = (0..40).map(|i| (4 + (i * 7) % 19, 3 + (i * 5) % 13)).collect();
+        pack_checked(&mut packer, &sizes);
+    }
+
+    #[test]
+    fn fills_area_exactly() {
+        let mut packer = SkylinePacker::new(64, 64);
+        let sizes = vec![(16, 16); 16];
+        pack_checked(&mut packer, &sizes);
+        assert_eq!(packer.pack(1, 1), None);
+    }
+
+    #[test]
+    fn rejects_too_large() {
+        let mut packer = SkylinePacker::new(32, 32);
+        assert_eq!(packer.pack(33, 1), None);
+        assert_eq!(packer.pack(1, 33), None);
+        assert!(packer.pack(32, 32).is_some());
+    }
+
+    #[test]
+    fn grows_atlas_when_needed() {
+        let sizes = vec![(200, 200); 4];
+        let (width, height, positions) = pack_all(&sizes, 1).unwrap();
+        assert_eq!((width, height), (512, 512));
+
+        let rectangles: Vec<Rectangle> = positions.iter().map(|p| Rectangle::new(p.x, p.y, 201, 201)).collect();
+        for (index, rectangle) in rectangles.iter().enumerate() {
+            assert!(Rectangle::new(0, 0, width as i32, height as i32).contains_rectangle(rectangle));
+            for other in &rectangles[index + 1..] {
+                assert!(!rectangle.overlaps_rectangle(other));
+            }
+        }
+    }
+
+    #[test]
+    fn blits_and_clips() {
+        let source = vec![255; 2 * 2 * 4];
+        let mut target = vec![0; 3 * 3 * 4];
+        blit_rgba(&source, 2, 2, &mut target, 3, 3, Point::new(2, 2));
+
+        let filled: Vec<usize> = target.chunks(4).enumerate().filter(|(_, pixel)| pixel[0] == 255).map(|(index, _)| index).collect();
+        assert_eq!(filled, vec![8]);
+    }
+}
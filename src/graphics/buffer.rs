@@ -6,15 +6,24 @@ use crate::{
         BufferType,
         BufferUsage,
         BufferId,
+        gl_call,
     },
 };
 
 unsafe fn create_buffer(gl: &glow::Context, target: u32, usage: u32, data: &[u8]) -> Result<BufferId, Error> {
     let buffer = gl.create_buffer()
-        .map_err(|error| NikoError::PlatformError(error))?;
+        .map_err(|_| NikoError::ContextLost)?;
 
-    gl.bind_buffer(target, Some(buffer));
-    gl.buffer_data_u8_slice(target, data, usage);
+    let result = (|| -> Result<(), Error> {
+        gl_call!(gl, bind_buffer(target, Some(buffer)));
+        gl_call!(gl, buffer_data_u8_slice(target, data, usage));
+        Ok(())
+    })();
+
+    if let Err(error) = result {
+        gl.delete_buffer(buffer);
+        return Err(error);
+    }
 
     Ok(buffer)
 }
@@ -33,6 +42,13 @@ impl Buffer {
         })
     }
 
+    /// deletes the buffer on the gpu
+    pub fn destroy(self, gl: &glow::Context) {
+        unsafe {
+            gl.delete_buffer(self.inner);
+        }
+    }
+
     pub(crate) fn get_inner(&self) -> BufferId {
         self.inner
     }
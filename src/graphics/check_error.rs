@@ -1,8 +1,8 @@
-use crate::Error;
+use crate::NikoError;
 use glow::{Context, HasContext};
 use thiserror::*;
 
-#[derive(Debug, Error)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Error)]
 pub enum WebGLError {
     #[error("An unacceptable value has been specified for an enumerated argument. The command is ignored and the error flag is set.")]
     InvalidEnum,
@@ -26,17 +26,55 @@ pub enum WebGLError {
     Unknown,
 }
 
-pub fn check_error(gl: &Context) -> Result<(), Error> {
-    unsafe {
-        match gl.get_error() {
-            glow::NO_ERROR => Ok(()),
-            glow::INVALID_ENUM => Err(WebGLError::InvalidEnum.into()),
-            glow::INVALID_VALUE => Err(WebGLError::InvalidValue.into()),
-            glow::INVALID_OPERATION => Err(WebGLError::InvalidOperation.into()),
-            glow::INVALID_FRAMEBUFFER_OPERATION => Err(WebGLError::InvalidFramebufferOperation.into()),
-            glow::OUT_OF_MEMORY => Err(WebGLError::OutOfMemory.into()),
-            glow::CONTEXT_LOST => Err(WebGLError::ContextLost.into()),
-            _ => Err(WebGLError::Unknown.into()),
+impl WebGLError {
+    /// converts a value returned by `getError`, `None` for `NO_ERROR`
+    pub fn from_code(code: u32) -> Option<Self> {
+        match code {
+            glow::NO_ERROR => None,
+            glow::INVALID_ENUM => Some(WebGLError::InvalidEnum),
+            glow::INVALID_VALUE => Some(WebGLError::InvalidValue),
+            glow::INVALID_OPERATION => Some(WebGLError::InvalidOperation),
+            glow::INVALID_FRAMEBUFFER_OPERATION => Some(WebGLError::InvalidFramebufferOperation),
+            glow::OUT_OF_MEMORY => Some(WebGLError::OutOfMemory),
+            glow::CONTEXT_LOST => Some(WebGLError::ContextLost),
+            _ => Some(WebGLError::Unknown),
         }
     }
 }
+
+/// returns the pending gl error, if any, as a `NikoError` naming the failing call
+pub fn check_error(gl: &Context, call: &str) -> Result<(), NikoError> {
+    let code = unsafe { gl.get_error() };
+    match WebGLError::from_code(code) {
+        None => Ok(()),
+        Some(WebGLError::ContextLost) => Err(NikoError::ContextLost),
+        Some(error) => Err(NikoError::GlError { call: call.to_string(), error }),
+    }
+}
+
+/// makes a gl call and in debug builds returns the gl error it caused as a `NikoError::GlError` naming the call,
+/// e.g. `gl_call!(gl, bind_buffer(glow::ARRAY_BUFFER, Some(buffer)))`
+macro_rules! gl_call {
+    ($gl:expr, $call:ident($($argument:expr),* $(,)?)) => {{
+        let gl: &glow::Context = $gl;
+        let result = gl.$call($($argument),*);
+        #[cfg(debug_assertions)]
+        $crate::graphics::check_error(gl, stringify!($call))?;
+        result
+    }};
+}
+
+pub(crate) use gl_call;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_error_codes() {
+        assert_eq!(WebGLError::from_code(glow::NO_ERROR), None);
+        assert_eq!(WebGLError::from_code(glow::INVALID_VALUE), Some(WebGLError::InvalidValue));
+        assert_eq!(WebGLError::from_code(glow::CONTEXT_LOST), Some(WebGLError::ContextLost));
+        assert_eq!(WebGLError::from_code(0x1234), Some(WebGLError::Unknown));
+    }
+}
@@ -0,0 +1,158 @@
+/// width of a glyph in font pixels
+pub const GLYPH_WIDTH: i32 = 3;
+/// height of a glyph in font pixels
+pub const GLYPH_HEIGHT: i32 = 5;
+/// horizontal distance between the start of two glyphs in font pixels
+pub const GLYPH_ADVANCE: i32 = GLYPH_WIDTH + 1;
+/// vertical distance between the top of two lines in font pixels
+pub const LINE_ADVANCE: i32 = GLYPH_HEIGHT + 2;
+
+/// a tiny 3x5 pixel font, every row is stored in the lower three bits with the leftmost pixel in the highest bit
+const GLYPHS: &[(char, [u8; 5])] = &[
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b111, 0b001, 0b111, 0b100, 0b111]),
+    ('3', [0b111, 0b001, 0b111, 0b001, 0b111]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b111, 0b001, 0b111]),
+    ('6', [0b111, 0b100, 0b111, 0b101, 0b111]),
+    ('7', [0b111, 0b001, 0b010, 0b010, 0b010]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+    ('9', [0b111, 0b101, 0b111, 0b001, 0b111]),
+    ('A', [0b010, 0b101, 0b111, 0b101, 0b101]),
+    ('B', [0b110, 0b101, 0b110, 0b101, 0b110]),
+    ('C', [0b011, 0b100, 0b100, 0b100, 0b011]),
+    ('D', [0b110, 0b101, 0b101, 0b101, 0b110]),
+    ('E', [0b111, 0b100, 0b110, 0b100, 0b111]),
+    ('F', [0b111, 0b100, 0b110, 0b100, 0b100]),
+    ('G', [0b011, 0b100, 0b101, 0b101, 0b011]),
+    ('H', [0b101, 0b101, 0b111, 0b101, 0b101]),
+    ('I', [0b111, 0b010, 0b010, 0b010, 0b111]),
+    ('J', [0b001, 0b001, 0b001, 0b101, 0b010]),
+    ('K', [0b101, 0b101, 0b110, 0b101, 0b101]),
+    ('L', [0b100, 0b100, 0b100, 0b100, 0b111]),
+    ('M', [0b101, 0b111, 0b111, 0b101, 0b101]),
+    ('N', [0b110, 0b101, 0b101, 0b101, 0b101]),
+    ('O', [0b010, 0b101, 0b101, 0b101, 0b010]),
+    ('P', [0b110, 0b101, 0b110, 0b100, 0b100]),
+    ('Q', [0b010, 0b101, 0b101, 0b110, 0b011]),
+    ('R', [0b110, 0b101, 0b110, 0b101, 0b101]),
+    ('S', [0b011, 0b100, 0b010, 0b001, 0b110]),
+    ('T', [0b111, 0b010, 0b010, 0b010, 0b010]),
+    ('U', [0b101, 0b101, 0b101, 0b101, 0b111]),
+    ('V', [0b101, 0b101, 0b101, 0b101, 0b010]),
+    ('W', [0b101, 0b101, 0b111, 0b111, 0b101]),
+    ('X', [0b101, 0b101, 0b010, 0b101, 0b101]),
+    ('Y', [0b101, 0b101, 0b010, 0b010, 0b010]),
+    ('Z', [0b111, 0b001, 0b010, 0b100, 0b111]),
+    ('.', [0b000, 0b000, 0b000, 0b000, 0b010]),
+    (',', [0b000, 0b000, 0b000, 0b010, 0b100]),
+    (':', [0b000, 0b010, 0b000, 0b010, 0b000]),
+    (';', [0b000, 0b010, 0b000, 0b010, 0b100]),
+    ('!', [0b010, 0b010, 0b010, 0b000, 0b010]),
+    ('?', [0b110, 0b001, 0b010, 0b000, 0b010]),
+    ('-', [0b000, 0b000, 0b111, 0b000, 0b000]),
+    ('+', [0b000, 0b010, 0b111, 0b010, 0b000]),
+    ('=', [0b000, 0b111, 0b000, 0b111, 0b000]),
+    ('_', [0b000, 0b000, 0b000, 0b000, 0b111]),
+    ('/', [0b001, 0b001, 0b010, 0b100, 0b100]),
+    ('\\', [0b100, 0b100, 0b010, 0b001, 0b001]),
+    ('%', [0b101, 0b001, 0b010, 0b100, 0b101]),
+    ('(', [0b001, 0b010, 0b010, 0b010, 0b001]),
+    (')', [0b100, 0b010, 0b010, 0b010, 0b100]),
+    ('[', [0b011, 0b010, 0b010, 0b010, 0b011]),
+    (']', [0b110, 0b010, 0b010, 0b010, 0b110]),
+    ('<', [0b001, 0b010, 0b100, 0b010, 0b001]),
+    ('>', [0b100, 0b010, 0b001, 0b010, 0b100]),
+    ('#', [0b101, 0b111, 0b101, 0b111, 0b101]),
+    ('*', [0b000, 0b101, 0b010, 0b101, 0b000]),
+    ('\'', [0b010, 0b010, 0b000, 0b000, 0b000]),
+    ('"', [0b101, 0b101, 0b000, 0b000, 0b000]),
+    ('|', [0b010, 0b010, 0b010, 0b010, 0b010]),
+];
+
+/// shown for characters the font does not contain
+const UNKNOWN: [u8; 5] = [0b111, 0b101, 0b101, 0b101, 0b111];
+
+fn glyph(character: char) -> Option<[u8; 5]> {
+    if character == ' ' {
+        return None;
+    }
+
+    let character = character.to_ascii_uppercase();
+    let rows = GLYPHS.iter()
+        .find(|(glyph, _)| *glyph == character)
+        .map(|(_, rows)| *rows)
+        .unwrap_or(UNKNOWN);
+
+    Some(rows)
+}
+
+/// lays out text and returns the filled font pixels as (column, row) offsets, rows grow downwards from the top left
+pub fn layout(text: &str) -> Vec<(i32, i32)> {
+    let mut pixels = Vec::new();
+    let mut line = 0;
+    let mut column = 0;
+
+    for character in text.chars() {
+        if character == '\n' {
+            line += 1;
+            column = 0;
+            continue;
+        }
+
+        if let Some(rows) = glyph(character) {
+            for (row, bits) in rows.iter().enumerate() {
+                for x in 0..GLYPH_WIDTH {
+                    if bits & (0b100 >> x) != 0 {
+                        pixels.push((column * GLYPH_ADVANCE + x, line * LINE_ADVANCE + row as i32));
+                    }
+                }
+            }
+        }
+
+        column += 1;
+    }
+
+    pixels
+}
+
+/// returns the size of the text in font pixels
+pub fn measure(text: &str) -> (i32, i32) {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let columns = lines.iter().map(|line| line.chars().count() as i32).max().unwrap_or(0);
+    let width = (columns * GLYPH_ADVANCE - 1).max(0);
+    let height = lines.len() as i32 * LINE_ADVANCE - (LINE_ADVANCE - GLYPH_HEIGHT);
+
+    (width, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lays_out_lines() {
+        let pixels = layout("1\n-");
+
+        // the one has 8 filled pixels, the minus 3 on its middle row one line below
+        assert_eq!(pixels.len(), 11);
+        assert!(pixels.contains(&(0, LINE_ADVANCE + 2)));
+        assert!(pixels.contains(&(2, LINE_ADVANCE + 2)));
+        assert!(!pixels.contains(&(0, LINE_ADVANCE)));
+    }
+
+    #[test]
+    fn skips_spaces_and_ignores_case() {
+        assert_eq!(layout(" "), vec![]);
+        assert_eq!(layout("a"), layout("A"));
+        assert_eq!(layout(" a")[0].0, layout("a")[0].0 + GLYPH_ADVANCE);
+    }
+
+    #[test]
+    fn measures_text() {
+        assert_eq!(measure("ab"), (7, 5));
+        assert_eq!(measure("abc\nd"), (11, 12));
+        assert_eq!(measure(""), (0, 5));
+    }
+}
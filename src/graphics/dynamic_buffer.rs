@@ -0,0 +1,79 @@
+use crate::{
+    Error,
+    graphics::{
+        Buffer,
+        BufferType,
+        BufferUsage,
+    },
+};
+
+/// collects vertices and indices on the cpu and uploads them as a pair of per-frame buffers
+pub(crate) struct DynamicBuffer {
+    vertex_data: Vec<f32>,
+    vertices: u16,
+    indices: Vec<u16>,
+}
+
+impl DynamicBuffer {
+    pub fn new() -> Self {
+        Self {
+            vertex_data: Vec::new(),
+            vertices: 0,
+            indices: Vec::new(),
+        }
+    }
+
+    /// appends one vertex made of all given components and returns its index
+    pub fn push_vertex(&mut self, data: &[f32]) -> u16 {
+        let index = self.vertices;
+        self.vertices += 1;
+
+        self.vertex_data.extend_from_slice(data);
+
+        index
+    }
+
+    pub fn push_triangle(&mut self, a: u16, b: u16, c: u16) {
+        self.indices.push(a);
+        self.indices.push(b);
+        self.indices.push(c);
+    }
+
+    pub fn push_quad(&mut self, a: u16, b: u16, c: u16, d: u16) {
+        self.push_triangle(a, b, c);
+        self.push_triangle(a, c, d);
+    }
+
+    /// the size of the vertex and index data in bytes
+    pub fn byte_len(&self) -> usize {
+        self.vertex_data.len() * std::mem::size_of::<f32>() + self.indices.len() * std::mem::size_of::<u16>()
+    }
+
+    pub fn build(self, gl: &glow::Context) -> Result<(i32, Buffer, Buffer), Error> {
+        use std::mem::size_of;
+        use std::slice::from_raw_parts;
+
+        let vertex_buffer = unsafe {
+            let byte_len = self.vertex_data.len() * size_of::<f32>();
+            let byte_data = from_raw_parts(self.vertex_data.as_ptr() as *const u8, byte_len);
+            Buffer::create(gl, BufferType::VertexBuffer, BufferUsage::DynamicDraw, byte_data)?
+        };
+
+        let index_buffer = unsafe {
+            let byte_len = self.indices.len() * size_of::<u16>();
+            let byte_data = from_raw_parts(self.indices.as_ptr() as *const u8, byte_len);
+            Buffer::create(gl, BufferType::IndexBuffer, BufferUsage::DynamicDraw, byte_data)
+        };
+        let index_buffer = match index_buffer {
+            Ok(index_buffer) => index_buffer,
+            Err(error) => {
+                vertex_buffer.destroy(gl);
+                return Err(error);
+            },
+        };
+
+        let count = self.indices.len() as i32;
+
+        Ok((count, vertex_buffer, index_buffer))
+    }
+}
@@ -1,9 +1,20 @@
 use thiserror::*;
 
+/// the kind of shader that failed to compile
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+}
+
 #[derive(Debug, Error)]
 pub enum ShaderError {
-    #[error("Error compiling shader: {0}")]
-    ShaderCompileError(String),
+    /// `annotated` contains the shader with line numbers and the messages of the log below the failing lines
+    #[error("Error compiling {stage:?} shader: {log}\n{annotated}")]
+    ShaderCompileError { stage: ShaderStage, log: String, annotated: String },
+
+    #[error("Error linking shader program: {0}")]
+    ShaderLinkError(String),
 
     #[error("No attribute with name {0} on shader")]
     AttributeNotFound(String),
@@ -1,31 +1,65 @@
-use crate::{Error, NikoError, graphics::TextureId};
-use glow::HasContext;
+use crate::{Error, NikoError, graphics::{TextureId, TextureOptions, WebGLError, gl_call}};
+use glow::{HasContext, PixelPackData};
 
-unsafe fn create_texture(gl: &glow::Context, width: u32, height: u32, data: &[u8]) -> Result<TextureId, Error> {
+unsafe fn create_texture(gl: &glow::Context, width: u32, height: u32, data: &[u8], options: TextureOptions) -> Result<TextureId, Error> {
     let texture = gl.create_texture()
-        .map_err(|error| NikoError::PlatformError(error))?;
-
-    gl.bind_texture(glow::TEXTURE_2D, Some(texture));
-
-    gl.tex_image_2d(
-        glow::TEXTURE_2D,
-        0,
-        glow::RGBA as i32,
-        width as i32,
-        height as i32,
-        0,
-        glow::RGBA,
-        glow::UNSIGNED_BYTE,
-        Some(data),
-    );
-
-    gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE as i32);
-    gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE as i32);
-    gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, glow::LINEAR as i32);
+        .map_err(|_| NikoError::ContextLost)?;
+
+    let result = (|| -> Result<(), Error> {
+        gl_call!(gl, bind_texture(glow::TEXTURE_2D, Some(texture)));
+
+        gl_call!(gl, tex_image_2d(
+            glow::TEXTURE_2D,
+            0,
+            glow::RGBA as i32,
+            width as i32,
+            height as i32,
+            0,
+            glow::RGBA,
+            glow::UNSIGNED_BYTE,
+            Some(data),
+        ));
+
+        options.apply(gl)
+    })();
+
+    if let Err(error) = result {
+        gl.delete_texture(texture);
+        return Err(error);
+    }
 
     Ok(texture)
 }
 
+/// reads the rgba pixels of a texture back by attaching it to a temporary framebuffer
+pub(crate) unsafe fn read_texture(gl: &glow::Context, texture: TextureId, width: u32, height: u32) -> Result<Vec<u8>, Error> {
+    let framebuffer = gl.create_framebuffer()
+        .map_err(|_| NikoError::ContextLost)?;
+
+    let mut data = vec![0; (width * height * 4) as usize];
+    let result = (|| -> Result<(), Error> {
+        gl_call!(gl, bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer)));
+        gl_call!(gl, framebuffer_texture_2d(glow::FRAMEBUFFER, glow::COLOR_ATTACHMENT0, glow::TEXTURE_2D, Some(texture), 0));
+
+        if gl.check_framebuffer_status(glow::FRAMEBUFFER) != glow::FRAMEBUFFER_COMPLETE {
+            return Err(NikoError::GlError { call: "framebufferTexture2D".to_string(), error: WebGLError::InvalidFramebufferOperation }.into());
+        }
+
+        gl_call!(gl, read_pixels(0, 0, width as i32, height as i32, glow::RGBA, glow::UNSIGNED_BYTE, PixelPackData::Slice(&mut data)));
+        Ok(())
+    })();
+
+    // the framebuffer is unbound even after a failed call, everything drawn later would end up in it otherwise
+    let cleanup = (|| -> Result<(), Error> {
+        gl_call!(gl, bind_framebuffer(glow::FRAMEBUFFER, None));
+        gl_call!(gl, delete_framebuffer(framebuffer));
+        Ok(())
+    })();
+
+    result.and(cleanup)?;
+    Ok(data)
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct Image {
     pub(crate) inner: TextureId,
@@ -33,11 +67,21 @@ pub struct Image {
 
 impl Image {
     pub fn create(gl: &glow::Context, width: u32, height: u32, data: &[u8]) -> Result<Self, Error> {
+        Self::create_with_options(gl, width, height, data, TextureOptions::default())
+    }
 
-        let inner = unsafe { create_texture(gl, width, height, data)? };
+    pub fn create_with_options(gl: &glow::Context, width: u32, height: u32, data: &[u8], options: TextureOptions) -> Result<Self, Error> {
+        let inner = unsafe { create_texture(gl, width, height, data, options)? };
 
         Ok(Self {
             inner,
         })
     }
+
+    /// deletes the texture on the gpu
+    pub fn destroy(self, gl: &glow::Context) {
+        unsafe {
+            gl.delete_texture(self.inner);
+        }
+    }
 }
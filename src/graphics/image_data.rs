@@ -0,0 +1,151 @@
+use crate::{Error, NikoError, assets::AssetLoader, graphics::qoi};
+
+const PNG_SIGNATURE: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+
+/// decoded rgba pixels of an image, rows go from top to bottom
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageData {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl ImageData {
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Result<Self, Error> {
+        let expected = width as usize * height as usize * 4;
+        if pixels.len() != expected {
+            return Err(NikoError::InvalidArgument(format!("expected {} bytes of rgba data for a {}x{} image, got {}", expected, width, height, pixels.len())).into());
+        }
+
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// decodes a png or qoi file, the format is detected from its first bytes
+    pub fn decode(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.starts_with(PNG_SIGNATURE) {
+            Self::decode_png(bytes)
+        } else if qoi::is_qoi(bytes) {
+            let (width, height, pixels) = qoi::decode(bytes)?;
+            Ok(Self { width, height, pixels })
+        } else {
+            Err(decode_error("png or qoi image", "unknown image format".to_string()))
+        }
+    }
+
+    pub fn decode_png(bytes: &[u8]) -> Result<Self, Error> {
+        let mut decoder = png::Decoder::new(bytes);
+        // palettes, transparency chunks and low bit depths are expanded, 16 bit channels are reduced to 8
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+
+        let mut reader = decoder.read_info()
+            .map_err(|error| decode_error("png image", error.to_string()))?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let frame = reader.next_frame(&mut buffer)
+            .map_err(|error| decode_error("png image", error.to_string()))?;
+        buffer.truncate(frame.buffer_size());
+
+        let pixels = match frame.color_type {
+            png::ColorType::Rgba => buffer,
+            png::ColorType::Rgb => buffer.chunks(3).flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255]).collect(),
+            png::ColorType::GrayscaleAlpha => buffer.chunks(2).flat_map(|pixel| [pixel[0], pixel[0], pixel[0], pixel[1]]).collect(),
+            png::ColorType::Grayscale => buffer.iter().flat_map(|value| [*value, *value, *value, 255]).collect(),
+            png::ColorType::Indexed => return Err(decode_error("png image", "palette was not expanded".to_string())),
+        };
+
+        Self::new(frame.width, frame.height, pixels)
+    }
+}
+
+/// reverses the order of the rows of rgba pixels in place
+pub(crate) fn flip_rows(pixels: &mut [u8], width: u32) {
+    let row_length = width as usize * 4;
+    if row_length == 0 {
+        return;
+    }
+
+    let rows = pixels.len() / row_length;
+    for row in 0..rows / 2 {
+        let (top, bottom) = pixels.split_at_mut((rows - 1 - row) * row_length);
+        top[row * row_length..(row + 1) * row_length].swap_with_slice(&mut bottom[..row_length]);
+    }
+}
+
+fn decode_error(asset: &str, reason: String) -> Error {
+    NikoError::DecodeError { asset: asset.to_string(), reason }.into()
+}
+
+/// decodes png and qoi files into `ImageData` assets
+#[derive(Debug, Default)]
+pub struct ImageDataLoader;
+
+impl AssetLoader for ImageDataLoader {
+    type Asset = ImageData;
+
+    fn extensions(&self) -> &[&'static str] {
+        &["png", "qoi"]
+    }
+
+    fn load(&mut self, _url: &str, bytes: &[u8]) -> Result<Self::Asset, Error> {
+        ImageData::decode(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(width: u32, height: u32, color_type: png::ColorType, data: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, width, height);
+            encoder.set_color(color_type);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(data).unwrap();
+        }
+        bytes
+    }
+
+    #[test]
+    fn decodes_rgba_png() {
+        let pixels = vec![255, 0, 0, 255, 0, 255, 0, 128];
+        let image = ImageData::decode(&encode(2, 1, png::ColorType::Rgba, &pixels)).unwrap();
+
+        assert_eq!(image, ImageData::new(2, 1, pixels).unwrap());
+    }
+
+    #[test]
+    fn expands_rgb_and_grayscale_png() {
+        let rgb = ImageData::decode(&encode(1, 2, png::ColorType::Rgb, &[1, 2, 3, 4, 5, 6])).unwrap();
+        assert_eq!(rgb.pixels, vec![1, 2, 3, 255, 4, 5, 6, 255]);
+
+        let gray = ImageData::decode(&encode(2, 1, png::ColorType::GrayscaleAlpha, &[7, 8, 9, 10])).unwrap();
+        assert_eq!(gray.pixels, vec![7, 7, 7, 8, 9, 9, 9, 10]);
+    }
+
+    #[test]
+    fn rejects_unknown_and_broken_data() {
+        assert!(ImageData::decode(b"GIF89a").is_err());
+
+        let mut png = encode(1, 1, png::ColorType::Rgba, &[1, 2, 3, 4]);
+        png.truncate(png.len() / 2);
+        assert!(ImageData::decode(&png).is_err());
+
+        assert!(ImageData::new(2, 2, vec![0; 4]).is_err());
+    }
+
+    #[test]
+    fn flips_rows() {
+        let mut pixels = vec![1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3];
+        flip_rows(&mut pixels, 1);
+        assert_eq!(pixels, vec![3, 3, 3, 3, 2, 2, 2, 2, 1, 1, 1, 1]);
+
+        let mut pixels = vec![1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4];
+        flip_rows(&mut pixels, 2);
+        assert_eq!(pixels, vec![3, 3, 3, 3, 4, 4, 4, 4, 1, 1, 1, 1, 2, 2, 2, 2]);
+    }
+}
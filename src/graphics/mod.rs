@@ -1,22 +1,41 @@
+mod animation;
+mod atlas;
 mod buffer;
 mod buffer_type;
 mod buffer_usage;
 mod image;
+mod image_data;
+mod nine_slice;
+mod qoi;
 mod shader;
 mod sprite;
 mod sprite_batch;
+mod sprite_sheet;
 mod error;
 mod check_error;
+mod dynamic_buffer;
+pub(crate) mod debug_font;
+mod shape_batch;
+mod texture_options;
 
+pub use animation::*;
+pub use atlas::*;
 pub use buffer::*;
 pub use buffer_type::*;
 pub use buffer_usage::*;
 pub use image::*;
+pub use image_data::*;
+pub use nine_slice::*;
 pub use shader::*;
+pub use shape_batch::*;
 pub use sprite::*;
 pub use sprite_batch::*;
+pub use sprite_sheet::*;
 pub use error::*;
-use check_error::*;
+pub use texture_options::*;
+pub use check_error::{WebGLError, check_error};
+pub(crate) use check_error::gl_call;
+pub(crate) use dynamic_buffer::*;
 
 pub type ShaderId = <glow::Context as glow::HasContext>::Shader;
 pub type ProgramId = <glow::Context as glow::HasContext>::Program;
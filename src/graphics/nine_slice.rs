@@ -0,0 +1,225 @@
+use crate::{Rectangle, graphics::Sprite};
+
+/// distances from the edges of a sprite that mark its borders
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Insets {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl Insets {
+    pub fn new(left: i32, top: i32, right: i32, bottom: i32) -> Self {
+        Self {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    /// the same inset on all four sides
+    pub fn uniform(inset: i32) -> Self {
+        Self::new(inset, inset, inset, inset)
+    }
+}
+
+/// how the edges and the center of a nine-slice fill their area
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum SliceMode {
+    Stretch,
+    Tile,
+}
+
+/// a sprite split into corners, edges and center so it can be drawn at any size without distorting its borders
+#[derive(Debug, Clone)]
+pub struct NineSlice {
+    pub(crate) sprite: Sprite,
+    insets: Insets,
+    edge_mode: SliceMode,
+    center_mode: SliceMode,
+}
+
+impl NineSlice {
+    /// creates a nine-slice that stretches its edges and center
+    pub fn new(sprite: Sprite, insets: Insets) -> Self {
+        Self {
+            sprite,
+            insets,
+            edge_mode: SliceMode::Stretch,
+            center_mode: SliceMode::Stretch,
+        }
+    }
+
+    pub fn with_edge_mode(self, edge_mode: SliceMode) -> Self {
+        Self {
+            edge_mode,
+            ..self
+        }
+    }
+
+    pub fn with_center_mode(self, center_mode: SliceMode) -> Self {
+        Self {
+            center_mode,
+            ..self
+        }
+    }
+
+    pub fn insets(&self) -> Insets {
+        self.insets
+    }
+
+    /// returns pairs of source and target rectangles that fill the target with this nine-slice
+    pub fn quads(&self, target: &Rectangle) -> Vec<(Rectangle, Rectangle)> {
+        let area = &self.sprite.area;
+        let insets = self.insets;
+
+        let source_columns = [
+            (area.x, insets.left),
+            (area.x + insets.left, area.width - insets.left - insets.right),
+            (area.x + area.width - insets.right, insets.right),
+        ];
+        let source_rows = [
+            (area.y, insets.top),
+            (area.y + insets.top, area.height - insets.top - insets.bottom),
+            (area.y + area.height - insets.bottom, insets.bottom),
+        ];
+
+        let (left, right) = fit_borders(insets.left, insets.right, target.width);
+        let (top, bottom) = fit_borders(insets.top, insets.bottom, target.height);
+        let target_columns = [
+            (target.x, left),
+            (target.x + left, target.width - left - right),
+            (target.x + target.width - right, right),
+        ];
+        let target_rows = [
+            (target.y, top),
+            (target.y + top, target.height - top - bottom),
+            (target.y + target.height - bottom, bottom),
+        ];
+
+        let mut quads = Vec::new();
+        for row in 0..3 {
+            for column in 0..3 {
+                let source = Rectangle::new(source_columns[column].0, source_rows[row].0, source_columns[column].1, source_rows[row].1);
+                let target = Rectangle::new(target_columns[column].0, target_rows[row].0, target_columns[column].1, target_rows[row].1);
+
+                let mode = match (row, column) {
+                    (1, 1) => self.center_mode,
+                    (1, _) | (_, 1) => self.edge_mode,
+                    _ => SliceMode::Stretch,
+                };
+
+                match mode {
+                    SliceMode::Stretch => push_quad(&mut quads, source, target),
+                    SliceMode::Tile => tile(&mut quads, &source, &target, column == 1, row == 1),
+                }
+            }
+        }
+
+        quads
+    }
+}
+
+/// shrinks both borders proportionally if they do not fit into the available size
+fn fit_borders(first: i32, second: i32, size: i32) -> (i32, i32) {
+    let total = first + second;
+    if total <= size || total == 0 {
+        return (first, second);
+    }
+
+    let size = size.max(0);
+    let first = first * size / total;
+    (first, size - first)
+}
+
+fn push_quad(quads: &mut Vec<(Rectangle, Rectangle)>, source: Rectangle, target: Rectangle) {
+    if source.width > 0 && source.height > 0 && target.width > 0 && target.height > 0 {
+        quads.push((source, target));
+    }
+}
+
+/// repeats the source at its original size along the given axes, cropping the last tile
+fn tile(quads: &mut Vec<(Rectangle, Rectangle)>, source: &Rectangle, target: &Rectangle, horizontal: bool, vertical: bool) {
+    if source.width <= 0 || source.height <= 0 {
+        return;
+    }
+
+    let step_x = if horizontal { source.width } else { target.width };
+    let step_y = if vertical { source.height } else { target.height };
+    if step_x <= 0 || step_y <= 0 {
+        return;
+    }
+
+    let mut y = 0;
+    while y < target.height {
+        let height = step_y.min(target.height - y);
+        let mut x = 0;
+        while x < target.width {
+            let width = step_x.min(target.width - x);
+            let source_width = if horizontal { width } else { source.width };
+            let source_height = if vertical { height } else { source.height };
+
+            push_quad(
+                quads,
+                Rectangle::new(source.x, source.y, source_width, source_height),
+                Rectangle::new(target.x + x, target.y + y, width, height),
+            );
+
+            x += step_x;
+        }
+        y += step_y;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Image;
+
+    fn panel() -> NineSlice {
+        NineSlice::new(Sprite::new(Image::detached(), Rectangle::new(0, 0, 12, 12)), Insets::uniform(4))
+    }
+
+    fn covered_area(quads: &[(Rectangle, Rectangle)]) -> i32 {
+        quads.iter().map(|(_, target)| target.width * target.height).sum()
+    }
+
+    #[test]
+    fn stretches_into_nine_quads() {
+        let target = Rectangle::new(10, 20, 100, 50);
+        let quads = panel().quads(&target);
+
+        assert_eq!(quads.len(), 9);
+        assert_eq!(covered_area(&quads), 100 * 50);
+        assert_eq!(quads[0], (Rectangle::new(0, 0, 4, 4), Rectangle::new(10, 20, 4, 4)));
+        assert_eq!(quads[4], (Rectangle::new(4, 4, 4, 4), Rectangle::new(14, 24, 92, 42)));
+        assert_eq!(quads[8], (Rectangle::new(8, 8, 4, 4), Rectangle::new(106, 66, 4, 4)));
+
+        for (_, quad) in &quads {
+            assert!(target.contains_rectangle(quad));
+        }
+    }
+
+    #[test]
+    fn tiles_edges_and_center() {
+        let target = Rectangle::new(0, 0, 18, 14);
+        let quads = panel().with_edge_mode(SliceMode::Tile).with_center_mode(SliceMode::Tile).quads(&target);
+
+        // 10x6 inner area with 4x4 tiles: 3 columns and 2 rows, the last ones cropped
+        assert_eq!(covered_area(&quads), 18 * 14);
+        assert_eq!(quads.len(), 4 + 3 * 2 + 2 * 2 + 3 * 2);
+
+        let cropped = quads.iter().find(|(_, target)| target.x == 12 && target.y == 8).unwrap();
+        assert_eq!(cropped.0, Rectangle::new(4, 4, 2, 2));
+    }
+
+    #[test]
+    fn shrinks_borders_of_small_targets() {
+        let quads = panel().quads(&Rectangle::new(0, 0, 6, 6));
+
+        assert_eq!(quads.len(), 4);
+        assert_eq!(covered_area(&quads), 36);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{Error, NikoError};
+
+const MAGIC: &[u8; 4] = b"qoif";
+const HEADER_SIZE: usize = 14;
+const END_MARKER: [u8; 8] = [0, 0, 0, 0, 0, 0, 0, 1];
+/// the reference implementation refuses larger images as well
+const MAX_PIXELS: usize = 400_000_000;
+
+const OP_INDEX: u8 = 0b0000_0000;
+const OP_DIFF: u8 = 0b0100_0000;
+const OP_LUMA: u8 = 0b1000_0000;
+// the decoder treats every remaining tag as a run, only the tests write it
+#[cfg(test)]
+const OP_RUN: u8 = 0b1100_0000;
+const OP_RGB: u8 = 0b1111_1110;
+const OP_RGBA: u8 = 0b1111_1111;
+const MASK: u8 = 0b1100_0000;
+
+pub(crate) fn is_qoi(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+fn hash(pixel: [u8; 4]) -> usize {
+    (pixel[0] as usize * 3 + pixel[1] as usize * 5 + pixel[2] as usize * 7 + pixel[3] as usize * 11) % 64
+}
+
+fn invalid(reason: &str) -> Error {
+    NikoError::DecodeError { asset: "qoi image".to_string(), reason: reason.to_string() }.into()
+}
+
+/// decodes a qoi image into its size and rgba pixels
+pub(crate) fn decode(bytes: &[u8]) -> Result<(u32, u32, Vec<u8>), Error> {
+    if bytes.len() < HEADER_SIZE + END_MARKER.len() || !is_qoi(bytes) {
+        return Err(invalid("not a qoi file"));
+    }
+
+    let width = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+    let height = u32::from_be_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]);
+    let pixel_count = (width as usize).checked_mul(height as usize)
+        .filter(|count| *count <= MAX_PIXELS)
+        .ok_or_else(|| invalid("image is too large"))?;
+    if width == 0 || height == 0 {
+        return Err(invalid("image is empty"));
+    }
+
+    let chunks = &bytes[HEADER_SIZE..bytes.len() - END_MARKER.len()];
+    let mut position = 0;
+    let mut next = || {
+        let byte = chunks.get(position).copied();
+        position += 1;
+        byte.ok_or_else(|| invalid("data ends early"))
+    };
+
+    // a run covers at most 62 pixels per byte, a header claiming more than the data can hold must not reserve it all
+    let mut pixels = Vec::with_capacity(pixel_count.min(chunks.len() * 62) * 4);
+    let mut index = [[0u8; 4]; 64];
+    let mut pixel = [0, 0, 0, 255];
+    let mut run = 0;
+
+    for _ in 0..pixel_count {
+        if run > 0 {
+            run -= 1;
+        } else {
+            let op = next()?;
+            match op {
+                OP_RGB => {
+                    pixel[0] = next()?;
+                    pixel[1] = next()?;
+                    pixel[2] = next()?;
+                },
+                OP_RGBA => {
+                    pixel = [next()?, next()?, next()?, next()?];
+                },
+                _ => match op & MASK {
+                    OP_INDEX => pixel = index[op as usize],
+                    OP_DIFF => {
+                        pixel[0] = pixel[0].wrapping_add((op >> 4) & 0b11).wrapping_sub(2);
+                        pixel[1] = pixel[1].wrapping_add((op >> 2) & 0b11).wrapping_sub(2);
+                        pixel[2] = pixel[2].wrapping_add(op & 0b11).wrapping_sub(2);
+                    },
+                    OP_LUMA => {
+                        let second = next()?;
+                        let green = (op & 0b0011_1111).wrapping_sub(32);
+                        pixel[0] = pixel[0].wrapping_add(green).wrapping_add(second >> 4).wrapping_sub(8);
+                        pixel[1] = pixel[1].wrapping_add(green);
+                        pixel[2] = pixel[2].wrapping_add(green).wrapping_add(second & 0b1111).wrapping_sub(8);
+                    },
+                    _ => run = op & 0b0011_1111,
+                },
+            }
+
+            index[hash(pixel)] = pixel;
+        }
+
+        pixels.extend_from_slice(&pixel);
+    }
+
+    Ok((width, height, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(width: u32, height: u32, chunks: &[u8]) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&width.to_be_bytes());
+        bytes.extend_from_slice(&height.to_be_bytes());
+        bytes.extend_from_slice(&[4, 0]);
+        bytes.extend_from_slice(chunks);
+        bytes.extend_from_slice(&END_MARKER);
+        bytes
+    }
+
+    #[test]
+    fn decodes_every_op() {
+        let red = [255, 0, 0, 255];
+        let chunks = [
+            OP_RGB, 255, 0, 0,
+            OP_RUN | 1,
+            OP_RGBA, 10, 20, 30, 40,
+            // +1 red, -1 green, +0 blue
+            OP_DIFF | (3 << 4) | (1 << 2) | 2,
+            // green +4, red +4 + 1, blue +4 - 2
+            OP_LUMA | (4 + 32), ((1 + 8) << 4) | (6),
+            OP_INDEX | hash(red) as u8,
+        ];
+        let (width, height, pixels) = decode(&file(7, 1, &chunks)).unwrap();
+
+        assert_eq!((width, height), (7, 1));
+        let pixels: Vec<&[u8]> = pixels.chunks(4).collect();
+        assert_eq!(pixels, vec![
+            &red[..],
+            &red[..],
+            &red[..],
+            &[10, 20, 30, 40][..],
+            &[11, 19, 30, 40][..],
+            &[16, 23, 32, 40][..],
+            &red[..],
+        ]);
+    }
+
+    #[test]
+    fn rejects_broken_files() {
+        assert!(decode(b"qoif").is_err());
+        assert!(decode(&file(2, 2, &[OP_RGB, 1, 2])).is_err());
+        assert!(decode(&file(0, 4, &[])).is_err());
+        assert!(decode(&file(u32::MAX, u32::MAX, &[])).is_err());
+    }
+}
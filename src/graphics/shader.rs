@@ -1,24 +1,62 @@
-use crate::{Error, NikoError, graphics::{ShaderError, check_error, ShaderId, ProgramId}};
+use crate::{Error, NikoError, graphics::{ShaderError, ShaderStage, ShaderId, ProgramId, gl_call}};
 use glow::HasContext;
 use std::collections::HashMap;
 
-macro_rules! log {
-    ( $( $t:tt )* ) => {
-        web_sys::console::log_1(&format!( $( $t )* ).into());
+/// returns the source with line numbers and the messages of a shader info log below the lines they refer to
+pub(crate) fn annotate_source(source: &str, log: &str) -> String {
+    let mut messages: Vec<(usize, &str)> = Vec::new();
+    for entry in log.lines() {
+        // entries look like `ERROR: 0:12: 'foo' : undeclared identifier`
+        let mut parts = entry.splitn(4, ':');
+        let (_, _, line, message) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(severity), Some(file), Some(line), Some(message)) => (severity, file, line, message),
+            _ => continue,
+        };
+        if let Ok(line) = line.trim().parse::<usize>() {
+            messages.push((line, message.trim()));
+        }
+    }
+
+    let width = source.lines().count().to_string().len();
+    let mut annotated = String::new();
+    for (index, text) in source.lines().enumerate() {
+        let number = index + 1;
+        let marker = if messages.iter().any(|(line, _)| *line == number) { ">>" } else { "  " };
+        annotated.push_str(&format!("{} {:>width$} | {}\n", marker, number, text, width = width));
+
+        for (_, message) in messages.iter().filter(|(line, _)| *line == number) {
+            annotated.push_str(&format!("   {:>width$} | ^ {}\n", "", message, width = width));
+        }
     }
+
+    annotated
 }
 
-unsafe fn compile_shader(gl: &glow::Context, source: &str, kind: u32) -> Result<ShaderId, Error> {
+unsafe fn compile_shader(gl: &glow::Context, source: &str, stage: ShaderStage) -> Result<ShaderId, Error> {
+    let kind = match stage {
+        ShaderStage::Vertex => glow::VERTEX_SHADER,
+        ShaderStage::Fragment => glow::FRAGMENT_SHADER,
+    };
+
     let shader = gl.create_shader(kind)
-        .map_err(|error| NikoError::PlatformError(error))?;
+        .map_err(|_| NikoError::ContextLost)?;
 
-    gl.shader_source(shader, source);
-    gl.compile_shader(shader);
+    let result = (|| -> Result<(), Error> {
+        gl_call!(gl, shader_source(shader, source));
+        gl_call!(gl, compile_shader(shader));
 
-    if !gl.get_shader_compile_status(shader) {
-        let error = gl.get_shader_info_log(shader);
+        if !gl.get_shader_compile_status(shader) {
+            let log = gl.get_shader_info_log(shader);
+            let annotated = annotate_source(source, &log);
+            return Err(NikoError::from(ShaderError::ShaderCompileError { stage, log, annotated }).into());
+        }
+
+        Ok(())
+    })();
+
+    if let Err(error) = result {
         gl.delete_shader(shader);
-        return Err(ShaderError::ShaderCompileError(error).into());
+        return Err(error);
     }
 
     Ok(shader)
@@ -29,10 +67,13 @@ unsafe fn reflect_attributes(gl: &glow::Context, program: ProgramId) -> HashMap<
     let attribute_count = gl.get_active_attributes(program);
     for index in 0..attribute_count {
         if let Some(attribute) = gl.get_active_attribute(program, index) {
-            log!("attribute found at {}: {}, {}, {}", index, attribute.name, attribute.atype, attribute.size);
-            attributes.insert(attribute.name, index);
+            // the index of an active attribute is not necessarily its location
+            if let Some(location) = gl.get_attrib_location(program, &attribute.name) {
+                log::debug!("attribute found at {}: {}, {}, {}", location, attribute.name, attribute.atype, attribute.size);
+                attributes.insert(attribute.name, location);
+            }
         } else {
-            log!("no attribute found at {}", index);
+            log::debug!("no attribute found at {}", index);
         }
     }
 
@@ -45,10 +86,10 @@ unsafe fn reflect_uniforms(gl: &glow::Context, program: ProgramId) -> HashMap<St
     for index in 0..uniform_count {
         if let Some(uniform) = gl.get_active_uniform(program, index) {
             if let Some(location) = gl.get_uniform_location(program, &uniform.name) {
-                log!("uniform found at {}: {}, {}, {}", index, uniform.name, uniform.utype, uniform.size);
+                log::debug!("uniform found at {}: {}, {}, {}", index, uniform.name, uniform.utype, uniform.size);
                 uniforms.insert(uniform.name, location);
             } else {
-                log!("no uniform found at {}", index);
+                log::debug!("no uniform found at {}", index);
             }
         }
     }
@@ -57,28 +98,51 @@ unsafe fn reflect_uniforms(gl: &glow::Context, program: ProgramId) -> HashMap<St
 }
 
 unsafe fn build_program(gl: &glow::Context, vertex_shader_source: &str, fragment_shader_source: &str) -> Result<ProgramId, Error> {
-    let vertex_shader = compile_shader(gl, vertex_shader_source, glow::VERTEX_SHADER)?;
-    let fragment_shader = compile_shader(gl, fragment_shader_source, glow::FRAGMENT_SHADER)?;
-    
-    let program = gl.create_program()
-        .map_err(|error| NikoError::PlatformError(error))?;
-
-    gl.attach_shader(program, vertex_shader);
-    gl.attach_shader(program, fragment_shader);
-    
-    gl.link_program(program);
-
-    if !gl.get_program_link_status(program) {
-        let error = gl.get_program_info_log(program);
-        gl.delete_program(program);
-        return Err(NikoError::PlatformError(error).into());
-    }
+    let vertex_shader = compile_shader(gl, vertex_shader_source, ShaderStage::Vertex)?;
+    let fragment_shader = match compile_shader(gl, fragment_shader_source, ShaderStage::Fragment) {
+        Ok(fragment_shader) => fragment_shader,
+        Err(error) => {
+            gl.delete_shader(vertex_shader);
+            return Err(error);
+        },
+    };
+
+    let program = match gl.create_program() {
+        Ok(program) => program,
+        Err(_) => {
+            gl.delete_shader(vertex_shader);
+            gl.delete_shader(fragment_shader);
+            return Err(NikoError::ContextLost.into());
+        },
+    };
+
+    let result = (|| -> Result<(), Error> {
+        gl_call!(gl, attach_shader(program, vertex_shader));
+        gl_call!(gl, attach_shader(program, fragment_shader));
+
+        gl_call!(gl, link_program(program));
+
+        if !gl.get_program_link_status(program) {
+            let error = gl.get_program_info_log(program);
+            return Err(NikoError::from(ShaderError::ShaderLinkError(error)).into());
+        }
 
-    gl.detach_shader(program, vertex_shader);
-    gl.detach_shader(program, fragment_shader);
+        gl_call!(gl, detach_shader(program, vertex_shader));
+        gl_call!(gl, detach_shader(program, fragment_shader));
+        Ok(())
+    })();
 
-    gl.delete_shader(vertex_shader);
-    gl.delete_shader(fragment_shader);
+    // the shaders are only needed for linking, a failed build deletes the program as well
+    let cleanup = (|| -> Result<(), Error> {
+        gl_call!(gl, delete_shader(vertex_shader));
+        gl_call!(gl, delete_shader(fragment_shader));
+        Ok(())
+    })();
+
+    if let Err(error) = result.and(cleanup) {
+        gl.delete_program(program);
+        return Err(error);
+    }
 
     Ok(program)
 }
@@ -104,6 +168,15 @@ impl Shader {
         })
     }
 
+    /// rebuilds the program from new sources, the old program is kept if they do not compile
+    pub fn reload(&mut self, gl: &glow::Context, vertex_shader: &str, fragment_shader: &str) -> Result<(), Error> {
+        let reloaded = Self::create(gl, vertex_shader, fragment_shader)?;
+        let old = std::mem::replace(self, reloaded);
+        old.destroy(gl);
+
+        Ok(())
+    }
+
     pub fn get_attribute_location(&self, name: &str) -> Option<u32> {
         match self.attributes.get(name) {
             Some(id) => Some(*id),
@@ -131,7 +204,41 @@ impl Shader {
         result
     }
 
+    /// deletes the shader program on the gpu
+    pub fn destroy(self, gl: &glow::Context) {
+        unsafe {
+            gl.delete_program(self.inner);
+        }
+    }
+
     pub(crate) fn get_inner(&self) -> ProgramId {
         self.inner
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn annotates_failing_lines() {
+        let source = "void main() {\n    gl_Position = foo;\n}";
+        let log = "ERROR: 0:2: 'foo' : undeclared identifier\nERROR: 0:2: '=' : cannot convert\n";
+
+        let annotated = annotate_source(source, log);
+        let lines: Vec<&str> = annotated.lines().collect();
+        assert_eq!(lines, vec![
+            "   1 | void main() {",
+            ">> 2 |     gl_Position = foo;",
+            "     | ^ 'foo' : undeclared identifier",
+            "     | ^ '=' : cannot convert",
+            "   3 | }",
+        ]);
+    }
+
+    #[test]
+    fn ignores_unparsable_log_entries() {
+        let annotated = annotate_source("a\nb", "something went wrong");
+        assert_eq!(annotated, "   1 | a\n   2 | b\n");
+    }
+}
@@ -0,0 +1,416 @@
+use crate::{
+    CANVAS_HEIGHT,
+    CANVAS_WIDTH,
+    Error,
+    NikoError,
+    Rectangle,
+    Context,
+    Color,
+    Point,
+    graphics::{DynamicBuffer, debug_font, gl_call},
+};
+use glow::HasContext;
+use std::f32::consts::PI;
+
+/// position and color components of a single vertex
+const VERTEX_SIZE: usize = 6;
+/// the vertices one draw call can index with u16, webgl2 always treats index 0xffff as a primitive restart
+const MAX_VERTICES: usize = u16::MAX as usize;
+
+/// draws solid colored lines, rectangles, circles, arcs and convex polygons, one draw call per 65535 vertices
+pub struct ShapeBatch {
+    /// earlier parts that ran out of indices, each is drawn with its own call
+    full: Vec<(Vec<f32>, Vec<u16>)>,
+    vertices: Vec<f32>,
+    indices: Vec<u16>,
+}
+
+impl ShapeBatch {
+    pub fn new() -> Self {
+        Self {
+            full: Vec::new(),
+            vertices: Vec::new(),
+            indices: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.full.is_empty() && self.indices.is_empty()
+    }
+
+    pub fn add_rectangle(&mut self, rectangle: &Rectangle, color: Color) {
+        if !self.reserve(4) {
+            return;
+        }
+
+        let left = rectangle.x as f32;
+        let bottom = rectangle.y as f32;
+        let right = (rectangle.x + rectangle.width) as f32;
+        let top = (rectangle.y + rectangle.height) as f32;
+
+        let a = self.push_vertex(left, bottom, color);
+        let b = self.push_vertex(right, bottom, color);
+        let c = self.push_vertex(right, top, color);
+        let d = self.push_vertex(left, top, color);
+        self.push_quad(a, b, c, d);
+    }
+
+    /// adds the border of a rectangle, the outline grows inwards
+    pub fn add_rectangle_outline(&mut self, rectangle: &Rectangle, thickness: i32, color: Color) {
+        let thickness = thickness.min(rectangle.width / 2).min(rectangle.height / 2).max(1);
+        let Rectangle { x, y, width, height } = *rectangle;
+
+        self.add_rectangle(&Rectangle::new(x, y, width, thickness), color);
+        self.add_rectangle(&Rectangle::new(x, y + height - thickness, width, thickness), color);
+        self.add_rectangle(&Rectangle::new(x, y + thickness, thickness, height - thickness * 2), color);
+        self.add_rectangle(&Rectangle::new(x + width - thickness, y + thickness, thickness, height - thickness * 2), color);
+    }
+
+    pub fn add_line(&mut self, from: Point, to: Point, thickness: i32, color: Color) {
+        self.push_line(from.x as f32, from.y as f32, to.x as f32, to.y as f32, thickness.max(1) as f32, color);
+    }
+
+    pub fn add_circle(&mut self, center: Point, radius: i32, color: Color) {
+        let segments = segments_for(radius as f32, 2.0 * PI);
+        if !self.reserve(segments + 1) {
+            return;
+        }
+        let (center_x, center_y) = (center.x as f32, center.y as f32);
+
+        let middle = self.push_vertex(center_x, center_y, color);
+        let first = self.vertex_count();
+        for segment in 0..segments {
+            let angle = segment as f32 / segments as f32 * 2.0 * PI;
+            self.push_vertex(center_x + angle.cos() * radius as f32, center_y + angle.sin() * radius as f32, color);
+        }
+
+        for segment in 0..segments {
+            let next = (segment + 1) % segments;
+            self.push_triangle(middle, first + segment as u16, first + next as u16);
+        }
+    }
+
+    /// adds the outline of a circle, the outline grows inwards
+    pub fn add_circle_outline(&mut self, center: Point, radius: i32, thickness: i32, color: Color) {
+        self.add_arc(center, radius, 0.0, 2.0 * PI, thickness, color);
+    }
+
+    /// adds a part of a circle outline between two angles in radians, counter-clockwise starting at the positive x axis
+    pub fn add_arc(&mut self, center: Point, radius: i32, start_angle: f32, end_angle: f32, thickness: i32, color: Color) {
+        let sweep = (end_angle - start_angle).min(2.0 * PI);
+        if sweep <= 0.0 || radius <= 0 {
+            return;
+        }
+
+        let segments = segments_for(radius as f32, sweep);
+        if !self.reserve((segments + 1) * 2) {
+            return;
+        }
+        let outer = radius as f32;
+        let inner = (radius - thickness.max(1)).max(0) as f32;
+        let (center_x, center_y) = (center.x as f32, center.y as f32);
+
+        let first = self.vertex_count();
+        for segment in 0..=segments {
+            let angle = start_angle + sweep * segment as f32 / segments as f32;
+            let (sin, cos) = angle.sin_cos();
+            self.push_vertex(center_x + cos * outer, center_y + sin * outer, color);
+            self.push_vertex(center_x + cos * inner, center_y + sin * inner, color);
+        }
+
+        for segment in 0..segments as u16 {
+            let outer_a = first + segment * 2;
+            let inner_a = outer_a + 1;
+            let outer_b = outer_a + 2;
+            let inner_b = outer_a + 3;
+            self.push_quad(inner_a, outer_a, outer_b, inner_b);
+        }
+    }
+
+    /// adds a filled convex polygon, the points have to be in order
+    pub fn add_polygon(&mut self, points: &[Point], color: Color) {
+        if points.len() < 3 || !self.reserve(points.len()) {
+            return;
+        }
+
+        let first = self.vertex_count();
+        for point in points {
+            self.push_vertex(point.x as f32, point.y as f32, color);
+        }
+
+        for index in 1..(points.len() - 1) as u16 {
+            self.push_triangle(first, first + index, first + index + 1);
+        }
+    }
+
+    /// adds the closed outline of a polygon
+    pub fn add_polygon_outline(&mut self, points: &[Point], thickness: i32, color: Color) {
+        if points.len() < 2 {
+            return;
+        }
+
+        for index in 0..points.len() {
+            let from = points[index];
+            let to = points[(index + 1) % points.len()];
+            self.add_line(from, to, thickness, color);
+        }
+    }
+
+    /// adds text using the built-in 3x5 pixel font, `position` is the top left corner and every font pixel is `scale` pixels large
+    pub fn add_debug_text(&mut self, position: Point, text: &str, scale: i32, color: Color) {
+        let scale = scale.max(1);
+        for (column, row) in debug_font::layout(text) {
+            let pixel = Rectangle::new(
+                position.x + column * scale,
+                position.y - (row + 1) * scale,
+                scale,
+                scale,
+            );
+            self.add_rectangle(&pixel, color);
+        }
+    }
+
+    pub fn draw(self, context: &mut Context) -> Result<(), Error> {
+        let ShapeBatch { mut full, vertices, indices } = self;
+        full.push((vertices, indices));
+
+        for (vertices, indices) in &full {
+            if !indices.is_empty() {
+                Self::draw_part(context, vertices, indices)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn draw_part(context: &mut Context, vertices: &[f32], indices: &[u16]) -> Result<(), Error> {
+        let gl = &context.gl;
+        let shader = &context.shape_shader;
+
+        let position_attribute = shader.get_attribute_location("position")
+            .ok_or_else(|| NikoError::from(super::ShaderError::AttributeNotFound("position".to_string())))?;
+        let color_attribute = shader.get_attribute_location("color")
+            .ok_or_else(|| NikoError::from(super::ShaderError::AttributeNotFound("color".to_string())))?;
+
+        let canvas_size = Rectangle::new(0, 0, CANVAS_WIDTH as i32, CANVAS_HEIGHT as i32);
+
+        let mut dynamic_buffer = DynamicBuffer::new();
+        for vertex in vertices.chunks(VERTEX_SIZE) {
+            let x = (vertex[0] / canvas_size.width as f32 - 0.5) * 2.0;
+            let y = (vertex[1] / canvas_size.height as f32 - 0.5) * 2.0;
+            dynamic_buffer.push_vertex(&[x, y, vertex[2], vertex[3], vertex[4], vertex[5]]);
+        }
+        for triangle in indices.chunks(3) {
+            dynamic_buffer.push_triangle(triangle[0], triangle[1], triangle[2]);
+        }
+        context.metrics.add_buffer_bytes(dynamic_buffer.byte_len());
+        let (count, vertex_buffer, index_buffer) = dynamic_buffer.build(gl)?;
+
+        // the buffers are deleted and the attributes disabled even if a call fails, later draws would use them otherwise
+        let drawn = unsafe {
+            (|| -> Result<(), Error> {
+                gl_call!(gl, enable(glow::BLEND));
+                gl_call!(gl, blend_func(glow::SRC_ALPHA, glow::ONE_MINUS_SRC_ALPHA));
+
+                gl_call!(gl, use_program(Some(shader.get_inner())));
+
+                let stride = (VERTEX_SIZE * std::mem::size_of::<f32>()) as i32;
+                gl_call!(gl, bind_buffer(glow::ARRAY_BUFFER, Some(vertex_buffer.get_inner())));
+                gl_call!(gl, vertex_attrib_pointer_f32(position_attribute, 2, glow::FLOAT, false, stride, 0));
+                gl_call!(gl, enable_vertex_attrib_array(position_attribute));
+                gl_call!(gl, vertex_attrib_pointer_f32(color_attribute, 4, glow::FLOAT, false, stride, 2 * std::mem::size_of::<f32>() as i32));
+                gl_call!(gl, enable_vertex_attrib_array(color_attribute));
+
+                gl_call!(gl, bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(index_buffer.get_inner())));
+                gl_call!(gl, draw_elements(glow::TRIANGLES, count, glow::UNSIGNED_SHORT, 0));
+                Ok(())
+            })()
+        };
+
+        let cleanup = unsafe {
+            (|| -> Result<(), Error> {
+                gl_call!(gl, disable_vertex_attrib_array(position_attribute));
+                gl_call!(gl, disable_vertex_attrib_array(color_attribute));
+                gl_call!(gl, delete_buffer(vertex_buffer.get_inner()));
+                gl_call!(gl, delete_buffer(index_buffer.get_inner()));
+                Ok(())
+            })()
+        };
+
+        drawn?;
+        cleanup?;
+
+        context.metrics.add_draw_calls(1);
+
+        Ok(())
+    }
+
+    /// makes room for a shape with `count` vertices, a full batch continues in a new part
+    ///
+    /// returns false for a shape too large for any part, it is skipped
+    fn reserve(&mut self, count: usize) -> bool {
+        if count > MAX_VERTICES {
+            log::warn!("skipping a shape with {} vertices, at most {} fit into a shape batch", count, MAX_VERTICES);
+            return false;
+        }
+
+        if self.vertices.len() / VERTEX_SIZE + count > MAX_VERTICES {
+            let vertices = std::mem::take(&mut self.vertices);
+            let indices = std::mem::take(&mut self.indices);
+            self.full.push((vertices, indices));
+        }
+
+        true
+    }
+
+    fn vertex_count(&self) -> u16 {
+        (self.vertices.len() / VERTEX_SIZE) as u16
+    }
+
+    fn push_vertex(&mut self, x: f32, y: f32, color: Color) -> u16 {
+        let index = self.vertex_count();
+        let (r, g, b, a) = color.into_normalized();
+        self.vertices.extend_from_slice(&[x, y, r, g, b, a]);
+        index
+    }
+
+    fn push_triangle(&mut self, a: u16, b: u16, c: u16) {
+        self.indices.extend_from_slice(&[a, b, c]);
+    }
+
+    fn push_quad(&mut self, a: u16, b: u16, c: u16, d: u16) {
+        self.push_triangle(a, b, c);
+        self.push_triangle(a, c, d);
+    }
+
+    fn push_line(&mut self, from_x: f32, from_y: f32, to_x: f32, to_y: f32, thickness: f32, color: Color) {
+        let (dx, dy) = (to_x - from_x, to_y - from_y);
+        let length = (dx * dx + dy * dy).sqrt();
+        if length <= 0.0 || !self.reserve(4) {
+            return;
+        }
+
+        // offset both ends perpendicular to the line by half the thickness
+        let (normal_x, normal_y) = (-dy / length * thickness / 2.0, dx / length * thickness / 2.0);
+
+        let a = self.push_vertex(from_x - normal_x, from_y - normal_y, color);
+        let b = self.push_vertex(to_x - normal_x, to_y - normal_y, color);
+        let c = self.push_vertex(to_x + normal_x, to_y + normal_y, color);
+        let d = self.push_vertex(from_x + normal_x, from_y + normal_y, color);
+        self.push_quad(a, b, c, d);
+    }
+}
+
+impl Default for ShapeBatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// picks enough segments for a curve to look round at the given radius
+fn segments_for(radius: f32, sweep: f32) -> usize {
+    let full_circle = (radius.max(1.0).sqrt() * 6.0).clamp(12.0, 96.0);
+    ((full_circle * sweep / (2.0 * PI)).ceil() as usize).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(batch: &ShapeBatch) -> Vec<(f32, f32)> {
+        batch.vertices.chunks(VERTEX_SIZE).map(|vertex| (vertex[0], vertex[1])).collect()
+    }
+
+    #[test]
+    fn rectangle_is_a_quad() {
+        let mut batch = ShapeBatch::new();
+        batch.add_rectangle(&Rectangle::new(10, 20, 30, 40), Color::rgb(255, 0, 0));
+
+        assert_eq!(positions(&batch), vec![(10.0, 20.0), (40.0, 20.0), (40.0, 60.0), (10.0, 60.0)]);
+        assert_eq!(batch.indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(&batch.vertices[2..6], &[1.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn outline_stays_inside() {
+        let mut batch = ShapeBatch::new();
+        let rectangle = Rectangle::new(0, 0, 20, 10);
+        batch.add_rectangle_outline(&rectangle, 2, Color::rgb(0, 0, 0));
+
+        assert_eq!(batch.indices.len(), 4 * 6);
+        for (x, y) in positions(&batch) {
+            assert!(rectangle.contains_point(Point::new(x as i32, y as i32)));
+        }
+    }
+
+    #[test]
+    fn line_has_thickness() {
+        let mut batch = ShapeBatch::new();
+        batch.add_line(Point::new(0, 0), Point::new(10, 0), 4, Color::rgb(0, 0, 0));
+
+        assert_eq!(positions(&batch), vec![(0.0, -2.0), (10.0, -2.0), (10.0, 2.0), (0.0, 2.0)]);
+    }
+
+    #[test]
+    fn circle_is_a_closed_fan() {
+        let mut batch = ShapeBatch::new();
+        batch.add_circle(Point::new(50, 50), 10, Color::rgb(0, 0, 0));
+
+        let vertices = positions(&batch);
+        let segments = vertices.len() - 1;
+        assert_eq!(batch.indices.len(), segments * 3);
+        assert_eq!(batch.indices[batch.indices.len() - 1], 1);
+
+        for (x, y) in &vertices[1..] {
+            let distance = ((x - 50.0).powi(2) + (y - 50.0).powi(2)).sqrt();
+            assert!((distance - 10.0).abs() < 0.001);
+        }
+    }
+
+    #[test]
+    fn arc_covers_its_sweep() {
+        let mut batch = ShapeBatch::new();
+        batch.add_arc(Point::new(0, 0), 10, 0.0, PI / 2.0, 2, Color::rgb(0, 0, 0));
+
+        let vertices = positions(&batch);
+        let (first_x, first_y) = vertices[0];
+        let (last_x, last_y) = vertices[vertices.len() - 2];
+        assert!((first_x - 10.0).abs() < 0.001 && first_y.abs() < 0.001);
+        assert!(last_x.abs() < 0.001 && (last_y - 10.0).abs() < 0.001);
+
+        let (inner_x, _) = vertices[1];
+        assert!((inner_x - 8.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn polygon_is_fanned() {
+        let mut batch = ShapeBatch::new();
+        let points = [Point::new(0, 0), Point::new(10, 0), Point::new(10, 10), Point::new(5, 15), Point::new(0, 10)];
+        batch.add_polygon(&points, Color::rgb(0, 0, 0));
+
+        assert_eq!(batch.indices, vec![0, 1, 2, 0, 2, 3, 0, 3, 4]);
+
+        batch.add_polygon(&points[..2], Color::rgb(0, 0, 0));
+        assert_eq!(batch.indices.len(), 9);
+    }
+
+    #[test]
+    fn splits_into_parts_that_fit_u16_indices() {
+        let mut batch = ShapeBatch::new();
+        for _ in 0..20_000 {
+            batch.add_rectangle(&Rectangle::new(0, 0, 1, 1), Color::rgb(0, 0, 0));
+        }
+
+        assert_eq!(batch.full.len(), 1);
+        let (vertices, indices) = &batch.full[0];
+        assert_eq!(vertices.len() / VERTEX_SIZE, MAX_VERTICES / 4 * 4);
+        assert!(indices.iter().all(|index| (*index as usize) < MAX_VERTICES));
+        assert_eq!(positions(&batch).len(), 80_000 - MAX_VERTICES / 4 * 4);
+        assert_eq!(batch.indices[..6], [0, 1, 2, 0, 2, 3]);
+
+        // a shape that does not fit into any part is skipped
+        let points: Vec<Point> = (0..MAX_VERTICES as i32 + 1).map(|x| Point::new(x, x % 2)).collect();
+        batch.add_polygon(&points, Color::rgb(0, 0, 0));
+        assert_eq!(batch.full.len(), 1);
+    }
+}
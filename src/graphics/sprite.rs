@@ -1,15 +1,23 @@
-use crate::{Error, Image, Rectangle, Point, Context};
+use crate::{Error, Image, Rectangle, Point, Context, graphics::TextureOptions};
 use std::rc::Rc;
 
 pub fn load_sprite(context: &mut Context, url: &str, size: Point) -> Result<Sprite, Error> {
+    load_sprite_with_options(context, url, size, TextureOptions::default())
+}
+
+pub fn load_sprite_with_options(context: &mut Context, url: &str, size: Point, options: TextureOptions) -> Result<Sprite, Error> {
     let event_queue_handle = Rc::clone(&context.event_queue);
-    let image = context.images.create_image_from_url(url, event_queue_handle)?;
+    let image = context.images.create_image_from_url(url, options, event_queue_handle)?;
     Ok(Sprite::new(image, Rectangle::new(0, 0, size.x, size.y)))
 }
 
 pub fn load_sprite_sheet(context: &mut Context, url: &str, sprite_size: Point, sheet_size: Point) -> Result<Vec<Sprite>, Error> {
+    load_sprite_sheet_with_options(context, url, sprite_size, sheet_size, TextureOptions::default())
+}
+
+pub fn load_sprite_sheet_with_options(context: &mut Context, url: &str, sprite_size: Point, sheet_size: Point, options: TextureOptions) -> Result<Vec<Sprite>, Error> {
     let event_queue_handle = Rc::clone(&context.event_queue);
-    let image = context.images.create_image_from_url(url, event_queue_handle)?;
+    let image = context.images.create_image_from_url(url, options, event_queue_handle)?;
 
     let num_sprites = sheet_size / sprite_size;
 
@@ -35,13 +43,62 @@ pub fn load_sprite_sheet(context: &mut Context, url: &str, sprite_size: Point, s
 pub struct Sprite {
     pub(crate) image: Image,
     pub(crate) area: Rectangle,
+    pub(crate) offset: Point,
+    pub(crate) size: Point,
+    pub(crate) pivot: Point,
 }
 
 impl Sprite {
     pub fn new(image: Image, area: Rectangle) -> Self {
+        let size = Point::new(area.width, area.height);
+        Self {
+            image,
+            area,
+            offset: Point::new(0, 0),
+            size,
+            pivot: Point::new(0, 0),
+        }
+    }
+
+    /// creates a sprite whose transparent border was trimmed away, `offset` is the position of `area` inside the untrimmed `size`, measured from the bottom left
+    pub fn trimmed(image: Image, area: Rectangle, offset: Point, size: Point) -> Self {
         Self {
             image,
             area,
+            offset,
+            size,
+            pivot: Point::new(0, 0),
+        }
+    }
+
+    /// returns a copy of this sprite with the pivot, relative to the bottom left of the untrimmed sprite
+    pub fn with_pivot(self, pivot: Point) -> Self {
+        Self {
+            pivot,
+            ..self
         }
     }
+
+    pub fn image(&self) -> Image {
+        self.image
+    }
+
+    /// the area of the image this sprite is cut from
+    pub fn area(&self) -> &Rectangle {
+        &self.area
+    }
+
+    /// the size of the sprite before it was trimmed
+    pub fn size(&self) -> Point {
+        self.size
+    }
+
+    /// the position of the trimmed area inside the untrimmed sprite, measured from the bottom left
+    pub fn offset(&self) -> Point {
+        self.offset
+    }
+
+    pub fn pivot(&self) -> Point {
+        self.pivot
+    }
 }
@@ -0,0 +1,403 @@
+use crate::{Error, NikoError, Image, Rectangle, Point, Context, graphics::{Sprite, TextureOptions}};
+use serde::{Deserialize, Deserializer, de::{MapAccess, Visitor}};
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+/// frame duration used when the sheet does not specify one
+const DEFAULT_FRAME_DURATION: u32 = 100;
+
+/// loads an image and cuts it into sprites described by a TexturePacker or Aseprite json export
+pub fn load_sprite_sheet_from_json(context: &mut Context, url: &str, json: &str) -> Result<SpriteSheet, Error> {
+    let data = SpriteSheetData::parse(json)?;
+
+    let event_queue_handle = Rc::clone(&context.event_queue);
+    let image = context.images.create_image_from_url(url, TextureOptions::default(), event_queue_handle)?;
+
+    Ok(data.into_sprite_sheet(image))
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ClipDirection {
+    Forward,
+    Reverse,
+    PingPong,
+    /// plays backward, then forward, then backward again
+    PingPongReverse,
+}
+
+/// a named sequence of frames, as exported from aseprite frame tags or texture packer animations
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SpriteSheetClip {
+    pub name: String,
+    /// indices into the frames of the sheet
+    pub frames: Vec<usize>,
+    /// duration of every frame in milliseconds
+    pub durations: Vec<u32>,
+    pub direction: ClipDirection,
+}
+
+/// a single frame of a sprite sheet, independent of any loaded image
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SpriteSheetFrame {
+    pub name: String,
+    pub area: Rectangle,
+    pub offset: Point,
+    pub size: Point,
+    pub pivot: Point,
+    pub duration: u32,
+}
+
+/// the parsed contents of a sprite sheet json file
+#[derive(Debug, Clone)]
+pub struct SpriteSheetData {
+    pub frames: Vec<SpriteSheetFrame>,
+    pub clips: Vec<SpriteSheetClip>,
+    /// the image file name stored in the sheet, if any
+    pub image: Option<String>,
+}
+
+fn decode_error(reason: String) -> NikoError {
+    NikoError::DecodeError {
+        asset: "sprite sheet".to_string(),
+        reason,
+    }
+}
+
+impl SpriteSheetData {
+    /// parses TexturePacker json (hash or array) and Aseprite json exports
+    pub fn parse(json: &str) -> Result<Self, Error> {
+        let raw: RawSheet = serde_json::from_str(json)
+            .map_err(|error| decode_error(error.to_string()))?;
+
+        let raw_frames = match raw.frames {
+            RawFrames::Array(frames) => frames.into_iter().map(|frame| (frame.filename, frame.frame)).collect(),
+            RawFrames::Hash(OrderedFrames(frames)) => frames,
+        };
+
+        let mut frames = Vec::with_capacity(raw_frames.len());
+        for (name, frame) in raw_frames {
+            if frame.rotated {
+                return Err(decode_error(format!("frame {} is rotated, rotated frames are not supported", name)).into());
+            }
+
+            let area = Rectangle::new(frame.frame.x, frame.frame.y, frame.frame.w, frame.frame.h);
+            let size = match &frame.source_size {
+                Some(size) => Point::new(size.w, size.h),
+                None => Point::new(area.width, area.height),
+            };
+            // the json measures from the top left, sprites are placed from the bottom left
+            let offset = match &frame.sprite_source_size {
+                Some(source) if frame.trimmed => Point::new(source.x, size.y - source.y - source.h),
+                _ => Point::new(0, 0),
+            };
+            let pivot = match &frame.pivot {
+                Some(pivot) => Point::new((pivot.x * size.x as f32).round() as i32, ((1.0 - pivot.y) * size.y as f32).round() as i32),
+                None => Point::new(0, 0),
+            };
+
+            frames.push(SpriteSheetFrame {
+                name,
+                area,
+                offset,
+                size,
+                pivot,
+                duration: frame.duration.unwrap_or(DEFAULT_FRAME_DURATION),
+            });
+        }
+
+        let mut clips = Vec::new();
+        for tag in raw.meta.frame_tags {
+            if tag.from > tag.to || tag.to >= frames.len() {
+                return Err(decode_error(format!("frame tag {} references frames {}..={} but the sheet has {} frames", tag.name, tag.from, tag.to, frames.len())).into());
+            }
+
+            let indices: Vec<usize> = (tag.from..=tag.to).collect();
+            let direction = match tag.direction.as_str() {
+                "reverse" => ClipDirection::Reverse,
+                "pingpong" => ClipDirection::PingPong,
+                "pingpong_reverse" => ClipDirection::PingPongReverse,
+                _ => ClipDirection::Forward,
+            };
+
+            clips.push(SpriteSheetClip {
+                name: tag.name,
+                durations: indices.iter().map(|index| frames[*index].duration).collect(),
+                frames: indices,
+                direction,
+            });
+        }
+
+        let mut animations: Vec<(String, Vec<String>)> = raw.animations.into_iter().collect();
+        animations.sort_by(|a, b| a.0.cmp(&b.0));
+        for (name, frame_names) in animations {
+            let mut indices = Vec::with_capacity(frame_names.len());
+            for frame_name in &frame_names {
+                let index = frames.iter().position(|frame| &frame.name == frame_name)
+                    .ok_or_else(|| decode_error(format!("animation {} references unknown frame {}", name, frame_name)))?;
+                indices.push(index);
+            }
+
+            clips.push(SpriteSheetClip {
+                name,
+                durations: indices.iter().map(|index| frames[*index].duration).collect(),
+                frames: indices,
+                direction: ClipDirection::Forward,
+            });
+        }
+
+        Ok(Self {
+            frames,
+            clips,
+            image: raw.meta.image,
+        })
+    }
+
+    /// creates sprites for all frames on the given image
+    pub fn into_sprite_sheet(self, image: Image) -> SpriteSheet {
+        let mut names = HashMap::new();
+        let mut sprites = Vec::with_capacity(self.frames.len());
+        for (index, frame) in self.frames.into_iter().enumerate() {
+            names.insert(frame.name, index);
+            sprites.push(Sprite::trimmed(image, frame.area, frame.offset, frame.size).with_pivot(frame.pivot));
+        }
+
+        SpriteSheet {
+            sprites,
+            names,
+            clips: self.clips,
+        }
+    }
+}
+
+/// sprites and animation clips loaded from a sprite sheet json file
+#[derive(Debug, Clone)]
+pub struct SpriteSheet {
+    sprites: Vec<Sprite>,
+    names: HashMap<String, usize>,
+    clips: Vec<SpriteSheetClip>,
+}
+
+impl SpriteSheet {
+    pub fn sprites(&self) -> &[Sprite] {
+        &self.sprites
+    }
+
+    /// finds a sprite by the frame name it had in the sheet
+    pub fn sprite(&self, name: &str) -> Option<&Sprite> {
+        self.names.get(name).map(|index| &self.sprites[*index])
+    }
+
+    pub fn clips(&self) -> &[SpriteSheetClip] {
+        &self.clips
+    }
+
+    pub fn clip(&self, name: &str) -> Option<&SpriteSheetClip> {
+        self.clips.iter().find(|clip| clip.name == name)
+    }
+}
+
+#[derive(Deserialize)]
+struct RawSheet {
+    frames: RawFrames,
+    #[serde(default)]
+    meta: RawMeta,
+    #[serde(default)]
+    animations: HashMap<String, Vec<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawFrames {
+    Array(Vec<RawNamedFrame>),
+    Hash(OrderedFrames),
+}
+
+#[derive(Deserialize)]
+struct RawNamedFrame {
+    filename: String,
+    #[serde(flatten)]
+    frame: RawFrame,
+}
+
+/// frames keyed by name, keeping the order of the file because frame tags refer to frames by index
+struct OrderedFrames(Vec<(String, RawFrame)>);
+
+impl<'de> Deserialize<'de> for OrderedFrames {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct OrderedFramesVisitor;
+
+        impl<'de> Visitor<'de> for OrderedFramesVisitor {
+            type Value = OrderedFrames;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map of frame names to frames")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut frames = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    frames.push(entry);
+                }
+
+                Ok(OrderedFrames(frames))
+            }
+        }
+
+        deserializer.deserialize_map(OrderedFramesVisitor)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawFrame {
+    frame: RawRectangle,
+    #[serde(default)]
+    rotated: bool,
+    #[serde(default)]
+    trimmed: bool,
+    sprite_source_size: Option<RawRectangle>,
+    source_size: Option<RawSize>,
+    pivot: Option<RawPivot>,
+    duration: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct RawRectangle {
+    x: i32,
+    y: i32,
+    w: i32,
+    h: i32,
+}
+
+#[derive(Deserialize)]
+struct RawSize {
+    w: i32,
+    h: i32,
+}
+
+#[derive(Deserialize)]
+struct RawPivot {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct RawMeta {
+    image: Option<String>,
+    #[serde(default)]
+    frame_tags: Vec<RawFrameTag>,
+}
+
+#[derive(Deserialize)]
+struct RawFrameTag {
+    name: String,
+    from: usize,
+    to: usize,
+    #[serde(default)]
+    direction: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ASEPRITE: &str = include_str!("../resources/fixtures/aseprite.json");
+    const TEXTURE_PACKER: &str = include_str!("../resources/fixtures/texture_packer.json");
+
+    #[test]
+    fn parses_aseprite() {
+        let data = SpriteSheetData::parse(ASEPRITE).unwrap();
+
+        assert_eq!(data.image.as_deref(), Some("hero.png"));
+        assert_eq!(data.frames.len(), 4);
+
+        let names: Vec<&str> = data.frames.iter().map(|frame| frame.name.as_str()).collect();
+        assert_eq!(names, vec!["hero 0.aseprite", "hero 1.aseprite", "hero 2.aseprite", "hero 3.aseprite"]);
+
+        let first = &data.frames[0];
+        assert_eq!(first.area, Rectangle::new(0, 0, 14, 16));
+        assert_eq!(first.offset, Point::new(1, 0));
+        assert_eq!(first.size, Point::new(16, 16));
+        assert_eq!(first.duration, 100);
+
+        // untrimmed frames have no offset
+        assert_eq!(data.frames[2].offset, Point::new(0, 0));
+
+        assert_eq!(data.clips.len(), 4);
+        assert_eq!(data.clips[0], SpriteSheetClip {
+            name: "idle".to_string(),
+            frames: vec![0, 1],
+            durations: vec![100, 150],
+            direction: ClipDirection::Forward,
+        });
+        assert_eq!(data.clips[1].frames, vec![1, 2, 3]);
+        assert_eq!(data.clips[1].direction, ClipDirection::PingPong);
+        assert_eq!(data.clips[2].direction, ClipDirection::Reverse);
+        assert_eq!(data.clips[3].frames, vec![0, 1, 2]);
+        assert_eq!(data.clips[3].direction, ClipDirection::PingPongReverse);
+    }
+
+    #[test]
+    fn measures_offsets_from_the_bottom() {
+        let data = SpriteSheetData::parse(ASEPRITE).unwrap();
+
+        // trimmed only at the top, the frame stays flush with the bottom
+        assert_eq!(data.frames[1].offset, Point::new(0, 0));
+        // trimmed only at the bottom, the frame is drawn above the two removed rows
+        assert_eq!(data.frames[3].area, Rectangle::new(46, 0, 16, 14));
+        assert_eq!(data.frames[3].offset, Point::new(0, 2));
+
+        let sheet = data.into_sprite_sheet(Image::detached());
+        assert_eq!(sheet.sprites()[3].offset(), Point::new(0, 2));
+    }
+
+    #[test]
+    fn parses_texture_packer() {
+        let data = SpriteSheetData::parse(TEXTURE_PACKER).unwrap();
+
+        assert_eq!(data.image.as_deref(), Some("items.png"));
+        assert_eq!(data.frames.len(), 3);
+
+        let coin = &data.frames[1];
+        assert_eq!(coin.name, "coin_02.png");
+        assert_eq!(coin.area, Rectangle::new(16, 2, 8, 12));
+        assert_eq!(coin.offset, Point::new(4, 2));
+        assert_eq!(coin.pivot, Point::new(8, 8));
+        assert_eq!(coin.duration, DEFAULT_FRAME_DURATION);
+
+        // a pivot at the bottom of the json is at the bottom of the sprite
+        assert_eq!(data.frames[2].pivot, Point::new(16, 0));
+
+        assert_eq!(data.clips.len(), 1);
+        assert_eq!(data.clips[0].name, "coin");
+        assert_eq!(data.clips[0].frames, vec![0, 1]);
+    }
+
+    #[test]
+    fn creates_named_sprites() {
+        let sheet = SpriteSheetData::parse(TEXTURE_PACKER).unwrap().into_sprite_sheet(Image::detached());
+
+        let door = sheet.sprite("door.png").unwrap();
+        assert_eq!(door.area(), &Rectangle::new(26, 2, 32, 48));
+        assert_eq!(door.size(), Point::new(32, 48));
+        assert!(sheet.sprite("missing.png").is_none());
+        assert_eq!(sheet.clip("coin").unwrap().frames.len(), 2);
+    }
+
+    #[test]
+    fn rejects_rotated_frames() {
+        let json = r#"{ "frames": { "a": { "frame": { "x": 0, "y": 0, "w": 1, "h": 1 }, "rotated": true } } }"#;
+        assert!(SpriteSheetData::parse(json).is_err());
+    }
+
+    #[test]
+    fn rejects_tags_out_of_range() {
+        let json = r#"{
+            "frames": { "a": { "frame": { "x": 0, "y": 0, "w": 1, "h": 1 } } },
+            "meta": { "frameTags": [ { "name": "broken", "from": 0, "to": 4, "direction": "forward" } ] }
+        }"#;
+        assert!(SpriteSheetData::parse(json).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+use glow::HasContext;
+use crate::{Error, graphics::gl_call};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum TextureFilter {
+    Nearest,
+    Linear,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum TextureWrap {
+    ClampToEdge,
+    Repeat,
+    MirroredRepeat,
+}
+
+impl From<TextureWrap> for u32 {
+    fn from(wrap: TextureWrap) -> u32 {
+        match wrap {
+            TextureWrap::ClampToEdge => glow::CLAMP_TO_EDGE,
+            TextureWrap::Repeat => glow::REPEAT,
+            TextureWrap::MirroredRepeat => glow::MIRRORED_REPEAT,
+        }
+    }
+}
+
+/// describes how a texture is sampled when it is drawn
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct TextureOptions {
+    pub min_filter: TextureFilter,
+    pub mag_filter: TextureFilter,
+    pub wrap_s: TextureWrap,
+    pub wrap_t: TextureWrap,
+    pub mipmaps: bool,
+}
+
+impl TextureOptions {
+    /// nearest filtering and clamped edges, keeps pixel art crisp
+    pub fn pixel_art() -> Self {
+        Self {
+            min_filter: TextureFilter::Nearest,
+            mag_filter: TextureFilter::Nearest,
+            wrap_s: TextureWrap::ClampToEdge,
+            wrap_t: TextureWrap::ClampToEdge,
+            mipmaps: false,
+        }
+    }
+
+    /// linear filtering with mipmaps and clamped edges, for smooth art that gets scaled
+    pub fn smooth() -> Self {
+        Self {
+            min_filter: TextureFilter::Linear,
+            mag_filter: TextureFilter::Linear,
+            wrap_s: TextureWrap::ClampToEdge,
+            wrap_t: TextureWrap::ClampToEdge,
+            mipmaps: true,
+        }
+    }
+
+    /// returns a copy of these options using the filter for minification and magnification
+    pub fn with_filter(self, filter: TextureFilter) -> Self {
+        Self {
+            min_filter: filter,
+            mag_filter: filter,
+            ..self
+        }
+    }
+
+    /// returns a copy of these options using the wrap mode in both directions
+    pub fn with_wrap(self, wrap: TextureWrap) -> Self {
+        Self {
+            wrap_s: wrap,
+            wrap_t: wrap,
+            ..self
+        }
+    }
+
+    /// returns a copy of these options with mipmap generation turned on or off
+    pub fn with_mipmaps(self, mipmaps: bool) -> Self {
+        Self {
+            mipmaps,
+            ..self
+        }
+    }
+
+    fn min_filter_parameter(&self) -> u32 {
+        match (self.min_filter, self.mipmaps) {
+            (TextureFilter::Nearest, false) => glow::NEAREST,
+            (TextureFilter::Linear, false) => glow::LINEAR,
+            (TextureFilter::Nearest, true) => glow::NEAREST_MIPMAP_NEAREST,
+            (TextureFilter::Linear, true) => glow::LINEAR_MIPMAP_LINEAR,
+        }
+    }
+
+    fn mag_filter_parameter(&self) -> u32 {
+        match self.mag_filter {
+            TextureFilter::Nearest => glow::NEAREST,
+            TextureFilter::Linear => glow::LINEAR,
+        }
+    }
+
+    /// applies these options to the texture currently bound to `TEXTURE_2D`
+    pub(crate) unsafe fn apply(&self, gl: &glow::Context) -> Result<(), Error> {
+        if self.mipmaps {
+            gl_call!(gl, generate_mipmap(glow::TEXTURE_2D));
+        }
+
+        gl_call!(gl, tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, u32::from(self.wrap_s) as i32));
+        gl_call!(gl, tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, u32::from(self.wrap_t) as i32));
+        gl_call!(gl, tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, self.min_filter_parameter() as i32));
+        gl_call!(gl, tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, self.mag_filter_parameter() as i32));
+
+        Ok(())
+    }
+}
+
+impl Default for TextureOptions {
+    fn default() -> Self {
+        Self::pixel_art()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_to_pixel_art() {
+        let options = TextureOptions::default();
+        assert_eq!(options, TextureOptions::pixel_art());
+        assert_eq!(options.min_filter_parameter(), glow::NEAREST);
+        assert_eq!(options.mag_filter_parameter(), glow::NEAREST);
+        assert_eq!(u32::from(options.wrap_s), glow::CLAMP_TO_EDGE);
+        assert!(!options.mipmaps);
+    }
+
+    #[test]
+    fn smooth_filters_linearly_with_mipmaps() {
+        let options = TextureOptions::smooth();
+        assert_eq!(options.min_filter_parameter(), glow::LINEAR_MIPMAP_LINEAR);
+        assert_eq!(options.mag_filter_parameter(), glow::LINEAR);
+        assert_eq!(options.with_mipmaps(false).min_filter_parameter(), glow::LINEAR);
+    }
+
+    #[test]
+    fn picks_the_mipmap_filter_for_the_min_filter() {
+        let options = TextureOptions::pixel_art().with_mipmaps(true);
+        assert_eq!(options.min_filter_parameter(), glow::NEAREST_MIPMAP_NEAREST);
+        // mipmaps never change magnification
+        assert_eq!(options.mag_filter_parameter(), glow::NEAREST);
+
+        let options = options.with_filter(TextureFilter::Linear).with_wrap(TextureWrap::Repeat);
+        assert_eq!(options.min_filter_parameter(), glow::LINEAR_MIPMAP_LINEAR);
+        assert_eq!(u32::from(options.wrap_t), glow::REPEAT);
+        assert_eq!(u32::from(TextureWrap::MirroredRepeat), glow::MIRRORED_REPEAT);
+    }
+}
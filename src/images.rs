@@ -1,107 +1,586 @@
 use std::rc::Rc;
 use std::cell::RefCell;
-use std::collections::{HashMap, VecDeque};
+use std::collections::VecDeque;
 use web_sys::{HtmlImageElement};
-use crate::{Error, NikoError, Event, event, graphics::TextureId};
-use glow::HasContext;
+use crate::{Error, NikoError, Event, Rectangle, event, collections::{Key as SlotKey, SlotList}, graphics::{self, ImageData, TextureId, TextureOptions}};
+use glow::{HasContext, PixelUnpackData};
 
+/// a handle to an image, handles of unloaded images are stale and no longer find anything
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct Image {
-    pub(crate) id: u32,
+    pub(crate) key: SlotKey,
 }
 
 impl Image {
-    pub(crate) fn new(id: u32) -> Self {
+    pub(crate) fn new(key: SlotKey) -> Self {
         Self {
-            id,
+            key,
+        }
+    }
+
+    /// a handle for tests that never points to a loaded image
+    #[cfg(test)]
+    pub(crate) fn detached() -> Self {
+        Self::new(SlotList::new().insert(()))
+    }
+}
+
+/// how far loading an image has come
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum LoadState {
+    Loading,
+    Loaded,
+    /// the image could not be downloaded, decoded or uploaded
+    Failed,
+}
+
+/// what a texture was created from, kept to re-create it after the webgl context was lost
+enum ImageSource {
+    Element(HtmlImageElement),
+    Pixels(Vec<u8>),
+}
+
+struct ImageEntry {
+    source: ImageSource,
+    state: LoadState,
+    texture: Option<TextureId>,
+    size: Option<(u32, u32)>,
+    options: TextureOptions,
+}
+
+impl ImageEntry {
+    /// creates the texture from the source, the size must already be known
+    /// the image only counts as loaded once its texture exists, a failed upload marks it as failed
+    fn upload(&mut self, gl: &glow::Context) -> Result<(), Error> {
+        let (width, height) = self.size.ok_or(NikoError::ImageNotLoaded)?;
+
+        let texture = match &self.source {
+            ImageSource::Pixels(pixels) => graphics::Image::create_with_options(gl, width, height, pixels, self.options).map(|image| image.inner),
+            ImageSource::Element(image) => unsafe { upload_element(gl, image, self.options) },
+        };
+
+        match texture {
+            Ok(texture) => {
+                self.texture = Some(texture);
+                self.state = LoadState::Loaded;
+                Ok(())
+            },
+            Err(error) => {
+                self.state = LoadState::Failed;
+                Err(error)
+            },
         }
     }
 }
 
 pub struct Images {
-    images: HashMap<u32, HtmlImageElement>,
-    textures: HashMap<u32, TextureId>,
-    sizes: HashMap<u32, (u32, u32)>,
-    next_id: u32,
+    entries: SlotList<ImageEntry>,
+    /// the number of reloads, used to make the urls of reloaded images unique
+    reloads: u32,
+    /// images that wait for asset packs to be mounted before they are loaded, with their urls
+    deferred: Vec<(Image, String)>,
 }
 
 impl Images {
     pub fn new() -> Self {
         Self {
-            images: HashMap::new(),
-            textures: HashMap::new(),
-            sizes: HashMap::new(),
-            next_id: 0,
+            entries: SlotList::new(),
+            reloads: 0,
+            deferred: Vec::new(),
+        }
+    }
+
+    pub(crate) fn create_image_from_url(&mut self, url: &str, options: TextureOptions, event_queue_handle: Rc<RefCell<VecDeque<Event>>>) -> Result<Image, Error> {
+        let image = self.insert_loading(options);
+        if let Err(error) = self.download(image, url, event_queue_handle) {
+            self.entries.remove(image.key);
+            return Err(error);
         }
+
+        Ok(image)
     }
 
-    pub(crate) fn create_image_from_url(&mut self, url: &str, event_queue_handle: Rc<RefCell<VecDeque<Event>>>) -> Result<Image, Error> {
-        let id = self.next_id;
-        self.next_id += 1;
+    /// creates an image that is loaded by `take_deferred` once no asset pack is downloading anymore
+    pub(crate) fn create_deferred_image(&mut self, url: &str, options: TextureOptions) -> Image {
+        let image = self.insert_loading(options);
+        self.deferred.push((image, url.to_string()));
+        image
+    }
+
+    /// returns the deferred images that were not unloaded meanwhile
+    pub(crate) fn take_deferred(&mut self) -> Vec<(Image, String)> {
+        let entries = &self.entries;
+        std::mem::take(&mut self.deferred).into_iter()
+            .filter(|(image, _)| entries.get(image.key).is_some())
+            .collect()
+    }
 
+    /// gives a loading image its decoded pixels, they are uploaded by `finish_loading`
+    pub(crate) fn set_pixels(&mut self, image: Image, data: ImageData) {
+        if let Some(entry) = self.entries.get_mut(image.key) {
+            entry.size = Some((data.width, data.height));
+            entry.source = ImageSource::Pixels(data.pixels);
+        }
+    }
+
+    fn insert_loading(&mut self, options: TextureOptions) -> Image {
+        Image::new(self.entries.insert(ImageEntry {
+            source: ImageSource::Pixels(Vec::new()),
+            state: LoadState::Loading,
+            texture: None,
+            size: None,
+            options,
+        }))
+    }
+
+    /// starts downloading a loading image with an image element, it reports back with `ImageLoaded` or `ImageFailed`
+    pub(crate) fn download(&mut self, image: Image, url: &str, event_queue_handle: Rc<RefCell<VecDeque<Event>>>) -> Result<(), Error> {
         let html_image = HtmlImageElement::new().map_err(|_| NikoError::PlatformError("could not create a new HtmlImageElement".to_string()))?;
+
+        let load_queue_handle = Rc::clone(&event_queue_handle);
+        let closures = event(&html_image, "load", move |_event: web_sys::Event| {
+            load_queue_handle.borrow_mut().push_back(Event::ImageLoaded(image));
+        }).and_then(|load| {
+            let error = event(&html_image, "error", move |_event: web_sys::Event| {
+                event_queue_handle.borrow_mut().push_back(Event::ImageFailed(image));
+            })?;
+            Ok((load, error))
+        });
+        let (load, error) = closures
+            .map_err(|_| NikoError::PlatformError("could not create a loading closure for an image".to_string()))?;
+        load.forget();
+        error.forget();
+
+        if let Some(entry) = self.entries.get_mut(image.key) {
+            entry.source = ImageSource::Element(html_image.clone());
+        }
         html_image.set_src(url);
 
-        let closure = event(&html_image, "load", move |_event: web_sys::Event| {
-            event_queue_handle.borrow_mut().push_back(Event::ImageLoaded(id));
-        }).map_err(|_| NikoError::PlatformError("could not create a loading closure for an image".to_string()))?;
-        closure.forget();
+        Ok(())
+    }
+
+    /// uploads rgba pixel data as a new image
+    pub(crate) fn create_image_from_pixels(&mut self, gl: &glow::Context, width: u32, height: u32, pixels: Vec<u8>, options: TextureOptions) -> Result<Image, Error> {
+        let texture = graphics::Image::create_with_options(gl, width, height, &pixels, options)?;
+        Ok(self.insert_texture(texture.inner, width, height, pixels, options))
+    }
+
+    /// registers a texture that was created from rgba pixel data instead of a url
+    pub(crate) fn insert_texture(&mut self, texture: TextureId, width: u32, height: u32, pixels: Vec<u8>, options: TextureOptions) -> Image {
+        Image::new(self.entries.insert(ImageEntry {
+            source: ImageSource::Pixels(pixels),
+            state: LoadState::Loaded,
+            texture: Some(texture),
+            size: Some((width, height)),
+            options,
+        }))
+    }
 
-        self.images.insert(id, html_image);
+    /// returns the rgba pixels of a loaded image, images loaded from a url are read back from the gpu
+    pub(crate) fn read_pixels(&self, image: Image, gl: &glow::Context) -> Result<Vec<u8>, Error> {
+        let entry = self.entries.get(image.key).ok_or(NikoError::ImageNotLoaded)?;
 
-        Ok(Image::new(id))
+        match (&entry.source, entry.texture, entry.size) {
+            (ImageSource::Pixels(pixels), _, Some(_)) => Ok(pixels.clone()),
+            (ImageSource::Element(_), Some(texture), Some((width, height))) => unsafe { graphics::read_texture(gl, texture, width, height) },
+            _ => Err(NikoError::ImageNotLoaded.into()),
+        }
     }
 
-    #[cfg(target_arch = "wasm32")]
-    pub(crate) fn finish_loading(&mut self, id: u32, gl: &glow::Context) -> Result<(), Error> {
-        let image = self.images.get(&id).unwrap();
-        let width = image.width();
-        let height = image.height();
-        self.sizes.insert(id, (width, height));
+    /// overwrites a region of a loaded image with rgba pixels
+    pub(crate) fn update_region(&mut self, image: Image, region: &Rectangle, rgba: &[u8], gl: &glow::Context) -> Result<(), Error> {
+        let (image_width, image_height) = self.find_size(image).ok_or(NikoError::ImageNotLoaded)?;
+        if !region_within(region, image_width, image_height) {
+            return Err(NikoError::InvalidArgument(format!("{:?} is outside of the {}x{} image", region, image_width, image_height)).into());
+        }
+        let expected = region.width as usize * region.height as usize * 4;
+        if rgba.len() != expected {
+            return Err(NikoError::InvalidArgument(format!("expected {} bytes of rgba data for the region, got {}", expected, rgba.len())).into());
+        }
 
-        let texture = unsafe {
-            let texture = gl.create_texture()
-                .map_err(|error| NikoError::PlatformError(error))?;
+        // images from urls keep a copy of their pixels from now on so they survive a lost context
+        if let Some(ImageSource::Element(_)) = self.entries.get(image.key).map(|entry| &entry.source) {
+            let pixels = self.read_pixels(image, gl)?;
+            if let Some(entry) = self.entries.get_mut(image.key) {
+                entry.source = ImageSource::Pixels(pixels);
+            }
+        }
 
-            gl.bind_texture(glow::TEXTURE_2D, Some(texture));
-            gl.tex_image_2d_with_html_image(
-                glow::TEXTURE_2D,
-                0,
-                glow::RGBA as i32,
-                glow::RGBA,
-                glow::UNSIGNED_BYTE,
-                &image,
-            );
+        let entry = self.entries.get_mut(image.key).ok_or(NikoError::ImageNotLoaded)?;
+        if let ImageSource::Pixels(pixels) = &mut entry.source {
+            write_region(pixels, image_width, region, rgba);
+        }
 
-            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE as i32);
-            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE as i32);
-            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, glow::NEAREST as i32);
-            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, glow::NEAREST as i32);
+        // without a context the texture is created from the updated pixels once it is restored
+        if let Some(texture) = entry.texture {
+            unsafe {
+                graphics::gl_call!(gl, bind_texture(glow::TEXTURE_2D, Some(texture)));
+                graphics::gl_call!(gl, tex_sub_image_2d(
+                    glow::TEXTURE_2D,
+                    0,
+                    region.x,
+                    region.y,
+                    region.width,
+                    region.height,
+                    glow::RGBA,
+                    glow::UNSIGNED_BYTE,
+                    PixelUnpackData::Slice(rgba),
+                ));
+            }
+        }
 
-            texture
+        Ok(())
+    }
+
+    /// uploads an image after its element finished loading
+    pub(crate) fn finish_loading(&mut self, image: Image, gl: &glow::Context) -> Result<(), Error> {
+        // the image was unloaded before it finished loading
+        let entry = match self.entries.get_mut(image.key) {
+            Some(entry) => entry,
+            None => return Ok(()),
         };
+        let size = match &entry.source {
+            ImageSource::Element(element) => (element.width(), element.height()),
+            // images read from a pack only need their first upload
+            ImageSource::Pixels(_) if entry.texture.is_none() => return entry.upload(gl),
+            ImageSource::Pixels(_) => return Ok(()),
+        };
+
+        // a reloaded image replaces its texture only once the new one was uploaded, the handle stays the same
+        let previous = entry.texture.take().map(|texture| (texture, entry.size));
+        entry.size = Some(size);
+        let result = entry.upload(gl);
+
+        match (result, previous) {
+            (Ok(()), Some((texture, _))) => {
+                unsafe {
+                    gl.delete_texture(texture);
+                }
+                Ok(())
+            },
+            (Err(error), Some((texture, size))) => {
+                entry.texture = Some(texture);
+                entry.size = size;
+                entry.state = LoadState::Loaded;
+                Err(error.context("could not upload a reloaded image, it keeps its old texture"))
+            },
+            (result, None) => result,
+        }
+    }
+
+    /// downloads images whose url points to the path again, they keep their old texture until the new one has loaded
+    pub(crate) fn reload(&mut self, path: &str) -> usize {
+        self.reloads += 1;
+
+        let mut reloaded = 0;
+        for (_, entry) in self.entries.iter_mut() {
+            if let ImageSource::Element(element) = &entry.source {
+                let url = element.src();
+                if url_matches_path(&url, path) {
+                    // the query makes the browser skip its cache
+                    element.set_src(&format!("{}?reload={}", without_query(&url), self.reloads));
+                    reloaded += 1;
+                }
+            }
+        }
+
+        reloaded
+    }
+
+    /// records the size of a loaded element without uploading it, used while the webgl context is lost
+    ///
+    /// the image stays loading until `restore` uploads it
+    pub(crate) fn record_size(&mut self, image: Image) {
+        if let Some(entry) = self.entries.get_mut(image.key) {
+            if let ImageSource::Element(element) = &entry.source {
+                entry.size = Some((element.width(), element.height()));
+            }
+        }
+    }
+
+    /// marks an image as failed and returns its url, an image that failed to reload stays loaded with its old texture
+    pub(crate) fn mark_failed(&mut self, image: Image) -> Option<String> {
+        let entry = self.entries.get_mut(image.key)?;
+        if entry.texture.is_none() {
+            entry.state = LoadState::Failed;
+        }
+
+        match &entry.source {
+            ImageSource::Element(element) => Some(element.src()),
+            ImageSource::Pixels(_) => None,
+        }
+    }
+
+    /// returns the loading state of an image, `None` if it was unloaded
+    pub fn load_state(&self, image: Image) -> Option<LoadState> {
+        self.entries.get(image.key).map(|entry| entry.state)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn set_load_state(&mut self, image: Image, state: LoadState) {
+        if let Some(entry) = self.entries.get_mut(image.key) {
+            entry.state = state;
+        }
+    }
 
-        self.textures.insert(id, texture);
+    /// re-creates all textures from their sources after the webgl context was restored
+    ///
+    /// a failed upload does not stop the others, the first error is returned once every image was tried
+    pub(crate) fn restore(&mut self, gl: &glow::Context) -> Result<(), Error> {
+        let mut first_error = None;
+        let mut failed = 0;
+        for (_, entry) in self.entries.iter_mut() {
+            // the old textures died with the context and must not be deleted
+            entry.texture = None;
+            if entry.size.is_some() {
+                if let Err(error) = entry.upload(gl) {
+                    failed += 1;
+                    match first_error {
+                        None => first_error = Some(error),
+                        Some(_) => log::error!("{:#}", error),
+                    }
+                }
+            }
+        }
+
+        match first_error {
+            Some(error) => Err(error.context(format!("could not restore {} image(s)", failed))),
+            None => Ok(()),
+        }
+    }
+
+    /// changes how an image is sampled, applied immediately if the image is already loaded
+    pub(crate) fn set_options(&mut self, image: Image, options: TextureOptions, gl: &glow::Context) -> Result<(), Error> {
+        if let Some(entry) = self.entries.get_mut(image.key) {
+            entry.options = options;
+
+            if let Some(texture) = entry.texture {
+                unsafe {
+                    graphics::gl_call!(gl, bind_texture(glow::TEXTURE_2D, Some(texture)));
+                    options.apply(gl)?;
+                }
+            }
+        }
 
         Ok(())
     }
 
-    #[cfg(not(target_arch = "wasm32"))]
-    pub(crate) fn finish_loading(&mut self, id: u32, gl: &glow::Context) -> Result<(), Error> {
-        unimplemented!()
+    /// forgets an image and returns its texture so it can be deleted, `None` if it was not loaded yet or already removed
+    pub(crate) fn remove(&mut self, image: Image) -> Option<TextureId> {
+        let entry = self.entries.remove(image.key)?;
+
+        // stops a download that is still running, its events would only find a stale handle
+        if let ImageSource::Element(element) = &entry.source {
+            if entry.state == LoadState::Loading {
+                let _ = element.remove_attribute("src");
+            }
+        }
+
+        entry.texture
+    }
+
+    /// returns true if the handle still refers to an image that has not been unloaded
+    pub fn contains(&self, image: Image) -> bool {
+        self.entries.contains(image.key)
+    }
+
+    /// iterates over all images that are loaded or still loading
+    pub fn iter(&self) -> impl Iterator<Item = Image> + '_ {
+        self.entries.iter().map(|(key, _)| Image::new(key))
+    }
+
+    /// the number of images that are loaded or still loading
+    pub fn image_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// the number of textures currently alive on the gpu
+    pub fn texture_count(&self) -> usize {
+        self.entries.iter().filter(|(_, entry)| entry.texture.is_some()).count()
     }
 
     pub fn find_texture(&self, image: Image) -> Option<TextureId> {
-        match self.textures.get(&image.id) {
-            Some(texture) => Some(*texture),
-            None => None,
-        }
+        self.entries.get(image.key).and_then(|entry| entry.texture)
     }
 
     pub fn find_size(&self, image: Image) -> Option<(u32, u32)> {
-        match self.sizes.get(&image.id) {
-            Some((width, height)) => Some((*width, *height)),
-            None => None,
-        }
+        self.entries.get(image.key).and_then(|entry| entry.size)
+    }
+}
+
+impl Default for Images {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn without_query(url: &str) -> &str {
+    url.split(['?', '#']).next().unwrap_or(url)
+}
+
+/// returns true if a url without its query ends with the path, e.g. `http://localhost/sprites/hero.png` and `sprites/hero.png`
+fn url_matches_path(url: &str, path: &str) -> bool {
+    let url = without_query(url);
+    let path = path.trim_start_matches("./").trim_start_matches('/');
+
+    !path.is_empty() && url.ends_with(path) && (url.len() == path.len() || url[..url.len() - path.len()].ends_with('/'))
+}
+
+/// returns true if a region has no negative values and fits into an area of the given size
+pub(crate) fn region_within(region: &Rectangle, width: u32, height: u32) -> bool {
+    region.x >= 0 && region.y >= 0 && region.width >= 0 && region.height >= 0
+        && region.x as i64 + region.width as i64 <= width as i64
+        && region.y as i64 + region.height as i64 <= height as i64
+}
+
+/// copies rgba rows of a region into the pixels of an image that is `image_width` pixels wide
+fn write_region(pixels: &mut [u8], image_width: u32, region: &Rectangle, rgba: &[u8]) {
+    let row_length = region.width as usize * 4;
+    for row in 0..region.height as usize {
+        let start = ((region.y as usize + row) * image_width as usize + region.x as usize) * 4;
+        pixels[start..start + row_length].copy_from_slice(&rgba[row * row_length..(row + 1) * row_length]);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+unsafe fn upload_element(gl: &glow::Context, image: &HtmlImageElement, options: TextureOptions) -> Result<TextureId, Error> {
+    let texture = gl.create_texture()
+        .map_err(|_| NikoError::ContextLost)?;
+
+    let result = (|| -> Result<(), Error> {
+        graphics::gl_call!(gl, bind_texture(glow::TEXTURE_2D, Some(texture)));
+        graphics::gl_call!(gl, tex_image_2d_with_html_image(
+            glow::TEXTURE_2D,
+            0,
+            glow::RGBA as i32,
+            glow::RGBA,
+            glow::UNSIGNED_BYTE,
+            image,
+        ));
+
+        options.apply(gl)
+    })();
+
+    if let Err(error) = result {
+        gl.delete_texture(texture);
+        return Err(error);
+    }
+
+    Ok(texture)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+unsafe fn upload_element(_gl: &glow::Context, _image: &HtmlImageElement, _options: TextureOptions) -> Result<TextureId, Error> {
+    unimplemented!()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removing_images_frees_everything() {
+        let mut images = Images::new();
+        let first = images.insert_texture(1, 2, 2, vec![0; 16], TextureOptions::default());
+        let second = images.insert_texture(2, 1, 1, vec![0; 4], TextureOptions::smooth());
+        assert_eq!(images.image_count(), 2);
+        assert_eq!(images.texture_count(), 2);
+
+        assert_eq!(images.remove(first), Some(1));
+        assert_eq!(images.find_texture(first), None);
+        assert_eq!(images.find_size(first), None);
+        assert_eq!(images.find_size(second), Some((1, 1)));
+
+        assert_eq!(images.remove(second), Some(2));
+        assert_eq!(images.image_count(), 0);
+        assert_eq!(images.texture_count(), 0);
+    }
+
+    #[test]
+    fn removing_twice_is_harmless() {
+        let mut images = Images::new();
+        let image = images.insert_texture(1, 1, 1, vec![0; 4], TextureOptions::default());
+
+        assert_eq!(images.remove(image), Some(1));
+        assert_eq!(images.remove(image), None);
+        assert_eq!(images.texture_count(), 0);
+    }
+
+    #[test]
+    fn failed_reloads_keep_the_image_loaded() {
+        let mut images = Images::new();
+        let loaded = images.insert_texture(1, 1, 1, vec![0; 4], TextureOptions::default());
+        images.mark_failed(loaded);
+        assert_eq!(images.load_state(loaded), Some(LoadState::Loaded));
+        assert_eq!(images.find_texture(loaded), Some(1));
+
+        let loading = images.insert_texture(2, 1, 1, vec![0; 4], TextureOptions::default());
+        images.entries.get_mut(loading.key).unwrap().texture = None;
+        images.set_load_state(loading, LoadState::Loading);
+        images.mark_failed(loading);
+        assert_eq!(images.load_state(loading), Some(LoadState::Failed));
+    }
+
+    #[test]
+    fn deferred_images_load_once_they_have_pixels() {
+        let mut images = Images::new();
+        let kept = images.create_deferred_image("sprites/hero.png", TextureOptions::default());
+        let unloaded = images.create_deferred_image("sprites/door.png", TextureOptions::default());
+        assert_eq!(images.load_state(kept), Some(LoadState::Loading));
+        assert_eq!(images.find_size(kept), None);
+
+        images.remove(unloaded);
+        assert_eq!(images.take_deferred(), vec![(kept, "sprites/hero.png".to_string())]);
+        assert!(images.take_deferred().is_empty());
+
+        images.set_pixels(kept, ImageData::new(2, 1, vec![0; 8]).unwrap());
+        assert_eq!(images.find_size(kept), Some((2, 1)));
+        assert_eq!(images.load_state(kept), Some(LoadState::Loading));
+    }
+
+    #[test]
+    fn stale_handles_do_not_find_reused_slots() {
+        let mut images = Images::new();
+        let old = images.insert_texture(1, 1, 1, vec![0; 4], TextureOptions::default());
+        images.remove(old);
+        let new = images.insert_texture(2, 4, 4, vec![0; 64], TextureOptions::default());
+
+        assert!(!images.contains(old));
+        assert_eq!(images.find_texture(old), None);
+        assert_eq!(images.find_texture(new), Some(2));
+        assert_eq!(images.iter().collect::<Vec<Image>>(), vec![new]);
+    }
+
+    #[test]
+    fn writes_regions_row_by_row() {
+        let mut pixels = vec![0; 3 * 2 * 4];
+        write_region(&mut pixels, 3, &Rectangle::new(1, 0, 2, 2), &[
+            1, 1, 1, 1, 2, 2, 2, 2,
+            3, 3, 3, 3, 4, 4, 4, 4,
+        ]);
+
+        assert_eq!(pixels, vec![
+            0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2,
+            0, 0, 0, 0, 3, 3, 3, 3, 4, 4, 4, 4,
+        ]);
+    }
+
+    #[test]
+    fn matches_urls_to_changed_paths() {
+        assert!(url_matches_path("http://localhost:8080/sprites/hero.png", "sprites/hero.png"));
+        assert!(url_matches_path("http://localhost:8080/sprites/hero.png?reload=3", "./sprites/hero.png"));
+        assert!(url_matches_path("hero.png", "hero.png"));
+        assert!(!url_matches_path("http://localhost:8080/sprites/superhero.png", "hero.png"));
+        assert!(!url_matches_path("http://localhost:8080/sprites/hero.png", "hero.jpg"));
+        assert!(!url_matches_path("http://localhost:8080/", ""));
+    }
+
+    #[test]
+    fn checks_that_regions_fit() {
+        assert!(region_within(&Rectangle::new(0, 0, 4, 2), 4, 2));
+        assert!(region_within(&Rectangle::new(3, 1, 1, 1), 4, 2));
+        assert!(!region_within(&Rectangle::new(3, 1, 2, 1), 4, 2));
+        assert!(!region_within(&Rectangle::new(-1, 0, 1, 1), 4, 2));
+        assert!(!region_within(&Rectangle::new(0, 0, i32::MAX, 1), 4, 2));
     }
 }
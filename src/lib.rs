@@ -1,32 +1,108 @@
+mod asset_group;
+mod assets;
+mod capture;
+mod config;
 mod context;
+mod debug;
+mod dev;
 mod error;
 mod game;
+mod game_loop;
 mod images;
 mod key;
-mod log;
+mod logger;
 pub mod graphics;
 mod resources;
 mod rectangle;
 mod point;
 mod color;
-mod collections;
+pub mod collections;
 mod random;
 mod input;
 mod metrics;
+mod time;
+mod profiler;
+mod storage;
 use metrics::*;
+pub use metrics::{
+    FrameStats,
+    MetricsSummary,
+    TimingSummary,
+    frame_stats,
+    metrics_summary,
+    set_metrics_overlay,
+    set_metrics_logging,
+};
 pub use rectangle::*;
 pub use point::*;
 pub use color::*;
 pub use random::*;
 pub use input::*;
-
+pub use time::*;
+pub use profiler::*;
+pub use storage::*;
+
+pub use asset_group::*;
+pub use assets::*;
+pub use capture::{
+    GifRecorder,
+    encode_png,
+    is_recording,
+    request_screenshot,
+    save_recording,
+    set_recording,
+};
+use capture::Capture;
+use resources::ShaderSources;
+pub use config::*;
 pub use context::*;
+pub use debug::*;
+pub use dev::{
+    DevChannel,
+    DevMessage,
+    StubChannel,
+    WebSocketChannel,
+    set_dev_channel,
+};
 pub use error::*;
-pub use log::*;
+pub use logger::*;
+pub use log::LevelFilter;
 pub use game::*;
+pub use game_loop::{pause, resume, is_paused};
+use game_loop::{LoopState, handle_error, is_running};
+use images::region_within;
 pub use images::*;
 pub use key::*;
-pub use graphics::{Sprite, SpriteBatch, load_sprite, load_sprite_sheet};
+pub use graphics::{
+    ShaderError,
+    ShaderStage,
+    WebGLError,
+    Animation,
+    AnimationEvent,
+    AnimationFrame,
+    AnimationPlayer,
+    PlaybackMode,
+    AtlasBuilder,
+    ImageData,
+    ImageDataLoader,
+    Insets,
+    NineSlice,
+    SliceMode,
+    ShapeBatch,
+    Sprite,
+    SpriteBatch,
+    SpriteSheet,
+    SpriteSheetClip,
+    ClipDirection,
+    TextureOptions,
+    TextureFilter,
+    TextureWrap,
+    load_sprite,
+    load_sprite_with_options,
+    load_sprite_sheet,
+    load_sprite_sheet_with_options,
+    load_sprite_sheet_from_json,
+};
 
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
@@ -42,13 +118,23 @@ use web_sys::{
 use glow::Context as GlowContext;
 use glow::HasContext;
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Event {
     KeyDown(Key),
     KeyUp(Key),
-    ImageLoaded(u32),
+    ImageLoaded(Image),
+    /// the image could not be downloaded or decoded, see `load_state`
+    ImageFailed(Image),
+    /// the webgl context was lost, the loop stops updating and drawing until it is restored
+    ContextLost,
+    /// the webgl context is back and all images have been re-created
+    ContextRestored,
 }
 
+/// the size of the drawing buffer in pixels
+pub const CANVAS_WIDTH: u32 = 1280;
+pub const CANVAS_HEIGHT: u32 = 720;
+
 pub fn clear(context: &mut Context, color: Color) {
     let (r, g, b, a) = color.into_normalized();
     unsafe {
@@ -58,24 +144,36 @@ pub fn clear(context: &mut Context, color: Color) {
 }
 
 #[cfg(target_arch = "wasm32")]
-fn create_context(canvas: web_sys::HtmlCanvasElement) -> Result<glow::Context, JsValue> {
+fn create_context(canvas: web_sys::HtmlCanvasElement) -> Result<glow::Context, NikoError> {
     let gl = canvas
-        .get_context("webgl2")?
-        .unwrap()
-        .dyn_into::<WebGl2RenderingContext>()?;
+        .get_context("webgl2")
+        .map_err(|error| NikoError::PlatformError(format!("{:?}", error)))?
+        .ok_or(NikoError::WebGlUnavailable)?
+        .dyn_into::<WebGl2RenderingContext>()
+        .map_err(|_| NikoError::WebGlUnavailable)?;
 
     Ok(GlowContext::from_webgl2_context(gl))
 }
 
 #[cfg(not(target_arch = "wasm32"))]
-fn create_context(canvas: web_sys::HtmlCanvasElement) -> Result<glow::Context, JsValue> {
+fn create_context(canvas: web_sys::HtmlCanvasElement) -> Result<glow::Context, NikoError> {
     unimplemented!()
 }
 
-pub fn run(mut game: Box<dyn Game>) -> Result<(), JsValue> {
-    let document = web_sys::window().unwrap().document().unwrap();
-    let canvas = document.get_element_by_id("canvas").unwrap();
-    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+pub fn run(game: Box<dyn Game>) -> Result<(), NikoError> {
+    run_with_config(game, Config::default())
+}
+
+pub fn run_with_config(mut game: Box<dyn Game>, config: Config) -> Result<(), NikoError> {
+    init_logger(config.log_level, config.log_filters.clone());
+
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or_else(|| NikoError::PlatformError("no document found".to_string()))?;
+    let canvas = document.get_element_by_id("canvas")
+        .ok_or_else(|| NikoError::CanvasNotFound("canvas".to_string()))?
+        .dyn_into::<web_sys::HtmlCanvasElement>()
+        .map_err(|_| NikoError::CanvasNotFound("canvas".to_string()))?;
     let event_queue = Rc::new(RefCell::new(VecDeque::new()));
     
     // Key down events
@@ -86,7 +184,7 @@ pub fn run(mut game: Box<dyn Game>) -> Result<(), JsValue> {
         if let Some(key) = into_key(event) {
             event_queue_handle.borrow_mut().push_back(Event::KeyDown(key));
         }
-    }).unwrap();
+    })?;
     closure.forget();
 
     // Key up events
@@ -97,7 +195,21 @@ pub fn run(mut game: Box<dyn Game>) -> Result<(), JsValue> {
         if let Some(key) = into_key(event) {
             event_queue_handle.borrow_mut().push_back(Event::KeyUp(key));
         }
-    }).unwrap();
+    })?;
+    closure.forget();
+
+    // the default of a lost context is to never be restored, prevent it so the browser restores it
+    let event_queue_handle = Rc::clone(&event_queue);
+    let closure = event(&canvas, "webglcontextlost", move |event: web_sys::Event| {
+        event.prevent_default();
+        event_queue_handle.borrow_mut().push_back(Event::ContextLost);
+    })?;
+    closure.forget();
+
+    let event_queue_handle = Rc::clone(&event_queue);
+    let closure = event(&canvas, "webglcontextrestored", move |_event: web_sys::Event| {
+        event_queue_handle.borrow_mut().push_back(Event::ContextRestored);
+    })?;
     closure.forget();
 
     let gl = create_context(canvas)?;
@@ -105,84 +217,305 @@ pub fn run(mut game: Box<dyn Game>) -> Result<(), JsValue> {
     let f = Rc::new(RefCell::new(None));
     let g = f.clone();
 
-    let sprite_shader = crate::graphics::Shader::create(
-        &gl,
-        crate::resources::SPRITE_BATCH_VERTEX,
-        crate::resources::SPRITE_BATCH_FRAGMENT
-    ).expect("could not create sprite shader");
+    let (sprite_shader, shape_shader) = create_shaders(&gl, &ShaderSources::default())
+        .map_err(|error| NikoError::InitializationError(format!("{:#}", error)))?;
 
     unsafe {
-        gl.viewport(0, 0, 1280, 720);
+        gl.viewport(0, 0, CANVAS_WIDTH as i32, CANVAS_HEIGHT as i32);
     }
 
-    let input = Input::new();
-    let metrics = Metrics::new();
-    
-    let mut context = Context::new(gl, event_queue, Images::new(), input, metrics, sprite_shader);
+    let mut context = Context::new(gl, event_queue, sprite_shader, shape_shader, &config);
+    set_metrics_overlay(&mut context, config.metrics_overlay);
+    set_metrics_logging(&mut context, config.metrics_logging);
+    set_profiling(config.profiling);
+    if let Some(url) = &config.dev_server {
+        match WebSocketChannel::connect(url) {
+            Ok(channel) => set_dev_channel(&mut context, Box::new(channel)),
+            Err(error) => log::warn!("hot reloading is off: {}", error),
+        }
+    }
 
-    game.initialize(&mut context).expect("Error while initializing");
+    if let Err(error) = game.initialize(&mut context) {
+        let message = format!("{:#}", error);
+        handle_error(game.as_mut(), &mut context, error, ErrorStage::Initialize);
+        if context.state == LoopState::Stopped {
+            return Err(NikoError::InitializationError(message));
+        }
+    }
 
     // game loop
     *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
-        context.metrics.reset();
-        while let Some(event) = context.event_queue.borrow_mut().pop_front() {
+        context.time.tick(now());
+        context.metrics.begin_frame(context.time.delta() as f64 * 1000.0);
+        let frame_scope = ProfileScope::new("frame");
+        context.assets.update();
+        load_deferred_images(&mut context);
+        dev::update(&mut context);
+        loop {
+            let event = context.event_queue.borrow_mut().pop_front();
+            let Some(event) = event else {
+                break;
+            };
+
             match event {
-                Event::ImageLoaded(id) => {
-                    context.images.finish_loading(id, &mut context.gl).expect("could not finish loading an image");
+                Event::ImageLoaded(image) => {
+                    if context.context_lost {
+                        // uploaded together with all other images once the context is restored
+                        context.images.record_size(image);
+                    } else if let Err(error) = context.images.finish_loading(image, &context.gl) {
+                        handle_error(game.as_mut(), &mut context, error, ErrorStage::Engine);
+                    }
+                },
+                Event::ImageFailed(image) => {
+                    // the browser does not tell whether the image was missing, broken or blocked
+                    if let Some(url) = context.images.mark_failed(image) {
+                        if context.images.load_state(image) == Some(LoadState::Loaded) {
+                            log::warn!("could not reload image {}, it keeps its old texture", url);
+                        } else {
+                            log::error!("could not load image {}", url);
+                        }
+                    }
                 },
                 Event::KeyDown(key) => {
+                    // keydown repeats while a key is held, only react to the first one
+                    if !context.input.is_key_down(key) {
+                        context.debug.key_pressed(key);
+                        context.capture.key_pressed(key);
+                    }
                     context.input.set_key(key);
                 },
                 Event::KeyUp(key) => {
                     context.input.reset_key(key);
                 },
+                Event::ContextLost => {
+                    log::warn!("webgl context lost");
+                    context.context_lost = true;
+                },
+                Event::ContextRestored => {
+                    log::info!("webgl context restored");
+                    context.context_lost = false;
+                    if let Err(error) = context.restore() {
+                        handle_error(game.as_mut(), &mut context, error, ErrorStage::Engine);
+                    }
+                },
             }
+
+            game.on_event(&mut context, &event);
         }
 
-        context.metrics.start_update();
-        game.update(&mut context).expect("Error while updating");
-        context.metrics.end_update();
+        context.metrics.start_update(now());
+        if is_running(&context) {
+            profile_scope!("update");
+            let result = if context.state == LoopState::Paused {
+                game.paused_update(&mut context)
+            } else {
+                game.update(&mut context)
+            };
+            if let Err(error) = result {
+                handle_error(game.as_mut(), &mut context, error, ErrorStage::Update);
+            }
+        }
+        context.metrics.end_update(now());
 
         unsafe {
-            context.gl.viewport(0, 0, 1280, 720);
+            context.gl.viewport(0, 0, CANVAS_WIDTH as i32, CANVAS_HEIGHT as i32);
+        }
+        context.metrics.start_draw(now());
+        if is_running(&context) {
+            profile_scope!("draw");
+            if let Err(error) = game.draw(&mut context) {
+                handle_error(game.as_mut(), &mut context, error, ErrorStage::Draw);
+            }
+            DebugDraw::flush(&mut context);
+        }
+        context.metrics.end_draw(now());
+
+        if is_running(&context) {
+            if let Err(error) = Metrics::draw_overlay(&mut context) {
+                handle_error(game.as_mut(), &mut context, error, ErrorStage::Engine);
+            }
+        }
+
+        // captures everything that was drawn, including the overlays
+        if is_running(&context) {
+            if let Err(error) = Capture::end_frame(&mut context) {
+                handle_error(game.as_mut(), &mut context, error, ErrorStage::Engine);
+            }
         }
-        context.metrics.start_draw();
-        game.draw(&mut context).expect("Error while drawing");
-        context.metrics.end_draw();
 
+        context.metrics.end_frame(context.images.texture_count());
         context.metrics.debug_print();
 
-        request_animation_frame(f.borrow().as_ref().unwrap());
+        drop(frame_scope);
+        with_profiler(|profiler| profiler.end_frame());
+
+        if context.state == LoopState::Stopped {
+            return;
+        }
+        if let Some(callback) = f.borrow().as_ref() {
+            request_animation_frame(callback);
+        }
     }) as Box<dyn FnMut()>));
 
-    request_animation_frame(g.borrow().as_ref().unwrap());
+    if let Some(callback) = g.borrow().as_ref() {
+        request_animation_frame(callback);
+    }
 
     Ok(())
 }
 
 pub fn load_image(context: &mut Context, url: &str) -> Result<Image, Error> {
+    load_image_with_options(context, url, TextureOptions::default())
+}
+
+/// images that are loaded while a pack is downloading wait for it, images in a mounted pack are read from it
+/// and everything else is downloaded, all of them send `Event::ImageLoaded` or `Event::ImageFailed`
+pub fn load_image_with_options(context: &mut Context, url: &str, options: TextureOptions) -> Result<Image, Error> {
+    if context.assets.has_pending_packs() || context.assets.is_packed(url) {
+        return Ok(context.images.create_deferred_image(url, options));
+    }
+
     let event_queue_handle = Rc::clone(&context.event_queue);
-    context.images.create_image_from_url(url, event_queue_handle)
+    context.images.create_image_from_url(url, options, event_queue_handle)
+}
+
+/// loads the images that waited for packs, called once per frame after the packs are updated
+fn load_deferred_images(context: &mut Context) {
+    if context.assets.has_pending_packs() {
+        return;
+    }
+
+    for (image, url) in context.images.take_deferred() {
+        let event = match context.assets.read_packed(&url) {
+            Some(bytes) => match bytes.map_err(Error::from).and_then(|bytes| ImageData::decode(&bytes)) {
+                Ok(data) => {
+                    context.images.set_pixels(image, data);
+                    Event::ImageLoaded(image)
+                },
+                Err(error) => {
+                    log::error!("could not read {} from its pack: {:#}", url, error);
+                    Event::ImageFailed(image)
+                },
+            },
+            None => {
+                let event_queue_handle = Rc::clone(&context.event_queue);
+                match context.images.download(image, &url, event_queue_handle) {
+                    Ok(()) => continue,
+                    Err(error) => {
+                        log::error!("could not download {}: {:#}", url, error);
+                        Event::ImageFailed(image)
+                    },
+                }
+            },
+        };
+
+        context.event_queue.borrow_mut().push_back(event);
+    }
+}
+
+/// decodes a png or qoi file and uploads it right away, e.g. for images embedded with `include_bytes!`
+pub fn load_image_from_bytes(context: &mut Context, bytes: &[u8]) -> Result<Image, Error> {
+    load_image_from_bytes_with_options(context, bytes, TextureOptions::default())
+}
+
+pub fn load_image_from_bytes_with_options(context: &mut Context, bytes: &[u8], options: TextureOptions) -> Result<Image, Error> {
+    let data = ImageData::decode(bytes)?;
+    context.images.create_image_from_pixels(&context.gl, data.width, data.height, data.pixels, options)
+}
+
+/// creates an image from rgba pixels, rows go from top to bottom
+pub fn create_image(context: &mut Context, width: u32, height: u32, rgba: &[u8]) -> Result<Image, Error> {
+    create_image_with_options(context, width, height, rgba, TextureOptions::default())
+}
+
+pub fn create_image_with_options(context: &mut Context, width: u32, height: u32, rgba: &[u8], options: TextureOptions) -> Result<Image, Error> {
+    let data = ImageData::new(width, height, rgba.to_vec())?;
+    context.images.create_image_from_pixels(&context.gl, data.width, data.height, data.pixels, options)
+}
+
+/// overwrites a region of a loaded image with rgba pixels, the region is measured from its top left corner
+pub fn update_image_region(context: &mut Context, image: Image, region: Rectangle, rgba: &[u8]) -> Result<(), Error> {
+    context.images.update_region(image, &region, rgba, &context.gl)
+}
+
+/// returns the rgba pixels of a loaded image
+pub fn read_image_pixels(context: &Context, image: Image) -> Result<ImageData, Error> {
+    let (width, height) = context.images.find_size(image).ok_or(NikoError::ImageNotLoaded)?;
+    ImageData::new(width, height, context.images.read_pixels(image, &context.gl)?)
+}
+
+/// reads a region of what has been drawn to the canvas this frame, the region is measured from its top left corner
+///
+/// webgl clears the canvas once a frame is shown, so this has to be called from `Game::draw`
+pub fn read_pixels(context: &Context, region: Rectangle) -> Result<ImageData, Error> {
+    if !region_within(&region, CANVAS_WIDTH, CANVAS_HEIGHT) {
+        return Err(NikoError::InvalidArgument(format!("{:?} is outside of the canvas", region)).into());
+    }
+
+    let (width, height) = (region.width as u32, region.height as u32);
+    let mut pixels = vec![0; width as usize * height as usize * 4];
+    unsafe {
+        graphics::gl_call!(&context.gl, read_pixels(
+            region.x,
+            CANVAS_HEIGHT as i32 - region.y - region.height,
+            region.width,
+            region.height,
+            glow::RGBA,
+            glow::UNSIGNED_BYTE,
+            glow::PixelPackData::Slice(&mut pixels),
+        ));
+    }
+
+    image_from_gl_rows(width, height, pixels)
+}
+
+/// turns pixels read back from webgl, which returns the bottom row first, into an image with the top row first
+pub(crate) fn image_from_gl_rows(width: u32, height: u32, mut pixels: Vec<u8>) -> Result<ImageData, Error> {
+    graphics::flip_rows(&mut pixels, width);
+    ImageData::new(width, height, pixels)
+}
+
+/// deletes an image and its texture and stops its download if it is still loading, sprites using it are skipped when drawn
+pub fn unload_image(context: &mut Context, image: Image) {
+    if let Some(texture) = context.images.remove(image) {
+        unsafe {
+            context.gl.delete_texture(texture);
+        }
+    }
+}
+
+/// changes filtering, wrapping and mipmapping of an image, also works while it is still loading
+pub fn set_texture_options(context: &mut Context, image: Image, options: TextureOptions) -> Result<(), Error> {
+    context.images.set_options(image, options, &context.gl)
 }
 
 fn window() -> web_sys::Window {
     web_sys::window().expect("no global `window` exists")
 }
 
+/// returns a high resolution timestamp in milliseconds
+fn now() -> f64 {
+    window()
+        .performance()
+        .expect("should have `performance` on window")
+        .now()
+}
+
 fn request_animation_frame(f: &Closure<dyn FnMut()>) {
     window()
         .request_animation_frame(f.as_ref().unchecked_ref())
         .expect("should register `requestAnimationFrame` OK");
 }
 
-fn event<F, T>(target: &EventTarget, event_name: &str, callback: F) -> Result<Closure<dyn FnMut(T)>, Error>
+fn event<F, T>(target: &EventTarget, event_name: &str, callback: F) -> Result<Closure<dyn FnMut(T)>, NikoError>
 where
     F: FnMut(T) + 'static,
     T: FromWasmAbi + 'static,
 {
     let callback = Closure::wrap(Box::new(callback) as Box<dyn FnMut(T)>);
 
-    target.add_event_listener_with_callback(event_name, callback.as_ref().unchecked_ref()).unwrap();
+    target.add_event_listener_with_callback(event_name, callback.as_ref().unchecked_ref())
+        .map_err(|_| NikoError::PlatformError(format!("could not listen to {} events", event_name)))?;
 
     Ok(callback)
 }
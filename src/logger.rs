@@ -0,0 +1,116 @@
+use wasm_bindgen::prelude::*;
+use web_sys::console;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use crate::Context;
+
+// TODO rust analyzer seems to get confused here about wherether unsafe is required or not
+#[allow(unused_unsafe)]
+pub fn log(_context: &Context, message: impl Into<JsValue>) {
+    unsafe {
+        console::log_1(&(message.into()));
+    }
+}
+
+/// installs the console logger as the `log` facade backend, only the first call has an effect
+pub fn init_logger(level: LevelFilter, filters: Vec<(String, LevelFilter)>) {
+    let logger = ConsoleLogger::new(level, filters);
+    let max_level = logger.max_level();
+
+    if log::set_boxed_logger(Box::new(logger)).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+/// forwards `log` records to the browser console using the console method matching their level
+pub struct ConsoleLogger {
+    level: LevelFilter,
+    filters: Vec<(String, LevelFilter)>,
+}
+
+impl ConsoleLogger {
+    /// creates a logger with a default level and per-target overrides, e.g. `("niko::graphics", LevelFilter::Debug)`
+    pub fn new(level: LevelFilter, filters: Vec<(String, LevelFilter)>) -> Self {
+        Self {
+            level,
+            filters,
+        }
+    }
+
+    /// returns the level for a target, using the most specific matching filter
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        self.filters.iter()
+            .filter(|(prefix, _)| target == prefix || target.starts_with(&format!("{}::", prefix)))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, level)| *level)
+            .unwrap_or(self.level)
+    }
+
+    /// the most verbose level any target can log at
+    pub fn max_level(&self) -> LevelFilter {
+        self.filters.iter()
+            .map(|(_, level)| *level)
+            .fold(self.level, |max, level| max.max(level))
+    }
+}
+
+impl Log for ConsoleLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level_for(metadata.target())
+    }
+
+    // TODO rust analyzer seems to get confused here about wherether unsafe is required or not
+    #[allow(unused_unsafe)]
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let message: JsValue = format!("[{} {}] {}", record.level(), record.target(), record.args()).into();
+        unsafe {
+            match record.level() {
+                Level::Error => console::error_1(&message),
+                Level::Warn => console::warn_1(&message),
+                Level::Info => console::info_1(&message),
+                Level::Debug | Level::Trace => console::debug_1(&message),
+            }
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::MetadataBuilder;
+
+    fn enabled(logger: &ConsoleLogger, level: Level, target: &str) -> bool {
+        logger.enabled(&MetadataBuilder::new().level(level).target(target).build())
+    }
+
+    #[test]
+    fn filters_by_most_specific_target() {
+        let logger = ConsoleLogger::new(LevelFilter::Warn, vec![
+            ("niko".to_string(), LevelFilter::Info),
+            ("niko::graphics".to_string(), LevelFilter::Debug),
+            ("game::ai".to_string(), LevelFilter::Off),
+        ]);
+
+        assert!(enabled(&logger, Level::Debug, "niko::graphics::shader"));
+        assert!(!enabled(&logger, Level::Debug, "niko::images"));
+        assert!(enabled(&logger, Level::Info, "niko::images"));
+        assert!(!enabled(&logger, Level::Error, "game::ai"));
+        assert!(enabled(&logger, Level::Warn, "game::physics"));
+        assert!(!enabled(&logger, Level::Info, "game"));
+
+        // a prefix only matches whole path segments
+        assert_eq!(logger.level_for("nikolai"), LevelFilter::Warn);
+    }
+
+    #[test]
+    fn max_level_covers_all_filters() {
+        let logger = ConsoleLogger::new(LevelFilter::Error, vec![("game".to_string(), LevelFilter::Trace)]);
+        assert_eq!(logger.max_level(), LevelFilter::Trace);
+        assert_eq!(ConsoleLogger::new(LevelFilter::Info, vec![]).max_level(), LevelFilter::Info);
+    }
+}
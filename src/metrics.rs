@@ -1,82 +1,362 @@
+use std::collections::VecDeque;
+use crate::{
+    CANVAS_HEIGHT,
+    Color,
+    Context,
+    Error,
+    Point,
+    Rectangle,
+    graphics::ShapeBatch,
+};
+
+/// number of frames kept for averages and percentiles
+const WINDOW_SIZE: usize = 120;
+
+/// returns the statistics of the last completed frame
+pub fn frame_stats(context: &Context) -> FrameStats {
+    context.metrics.last
+}
+
+/// returns averages and percentiles over the last frames
+pub fn metrics_summary(context: &Context) -> MetricsSummary {
+    context.metrics.summary()
+}
+
+/// shows or hides a graph of frame times and the current statistics on top of the game
+pub fn set_metrics_overlay(context: &mut Context, enabled: bool) {
+    context.metrics.overlay = enabled;
+}
+
+/// turns logging a summary to the console every few seconds on or off
+pub fn set_metrics_logging(context: &mut Context, enabled: bool) {
+    context.metrics.logging = enabled;
+}
+
+/// statistics of a single frame
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct FrameStats {
+    /// time spent in `Game::update` in milliseconds
+    pub update_ms: f64,
+    /// time spent in `Game::draw` in milliseconds
+    pub draw_ms: f64,
+    /// time since the previous frame in milliseconds
+    pub frame_ms: f64,
+    pub draw_calls: usize,
+    pub sprites_drawn: usize,
+    pub sprites_skipped: usize,
+    /// number of textures alive on the gpu
+    pub texture_count: usize,
+    /// bytes uploaded into vertex and index buffers
+    pub buffer_bytes: usize,
+}
+
+impl FrameStats {
+    pub fn fps(&self) -> f64 {
+        if self.frame_ms > 0.0 {
+            1000.0 / self.frame_ms
+        } else {
+            0.0
+        }
+    }
+}
+
+/// distribution of a timing over the metrics window, all values in milliseconds
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct TimingSummary {
+    pub average: f64,
+    pub median: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl TimingSummary {
+    fn from_samples(mut samples: Vec<f64>) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+
+        samples.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+        Self {
+            average: samples.iter().sum::<f64>() / samples.len() as f64,
+            median: percentile(&samples, 0.5),
+            p95: percentile(&samples, 0.95),
+            p99: percentile(&samples, 0.99),
+            max: samples[samples.len() - 1],
+        }
+    }
+}
+
+/// rolling statistics over the metrics window
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct MetricsSummary {
+    /// number of frames the summary is based on
+    pub frames: usize,
+    pub frame: TimingSummary,
+    pub update: TimingSummary,
+    pub draw: TimingSummary,
+    /// frames per second based on the average frame time
+    pub fps: f64,
+    pub average_draw_calls: f64,
+    pub average_sprites_drawn: f64,
+}
+
+/// returns the nearest-rank percentile of already sorted samples
+fn percentile(sorted: &[f64], fraction: f64) -> f64 {
+    let rank = (fraction * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
 pub struct Metrics {
-    draw_calls: usize,
-    sprites_drawn: usize,
-    sprites_skipped: usize,
+    current: FrameStats,
+    last: FrameStats,
+    history: VecDeque<FrameStats>,
 
     update_start: f64,
-    update_end: f64,
-    
     draw_start: f64,
-    draw_end: f64,
 
+    overlay: bool,
+    logging: bool,
     ticks: usize,
 }
 
 impl Metrics {
     pub fn new() -> Self {
         Self {
-            draw_calls: 0,
-            sprites_drawn: 0,
-            sprites_skipped: 0,
+            current: FrameStats::default(),
+            last: FrameStats::default(),
+            history: VecDeque::with_capacity(WINDOW_SIZE),
 
             update_start: 0.0,
-            update_end: 0.0,
-            
             draw_start: 0.0,
-            draw_end: 0.0,
 
-            ticks: 99,
+            overlay: false,
+            logging: false,
+            ticks: 0,
         }
     }
 
-    pub fn reset(&mut self) {
-        self.draw_calls = 0;
-        self.sprites_drawn = 0;
-        self.sprites_skipped = 0;
+    /// starts collecting a new frame, `frame_ms` is the time since the previous frame
+    pub fn begin_frame(&mut self, frame_ms: f64) {
+        self.current = FrameStats {
+            frame_ms,
+            ..FrameStats::default()
+        };
     }
 
-    pub fn start_update(&mut self) {
-        self.update_start = web_sys::window().unwrap().performance().unwrap().now();
+    /// finishes the current frame and adds it to the window
+    pub fn end_frame(&mut self, texture_count: usize) {
+        self.current.texture_count = texture_count;
+        self.last = self.current;
+
+        if self.history.len() == WINDOW_SIZE {
+            self.history.pop_front();
+        }
+        self.history.push_back(self.current);
     }
 
-    pub fn end_update(&mut self) {
-        self.update_end = web_sys::window().unwrap().performance().unwrap().now();
+    pub fn start_update(&mut self, now: f64) {
+        self.update_start = now;
     }
 
-    pub fn start_draw(&mut self) {
-        self.draw_start = web_sys::window().unwrap().performance().unwrap().now();
+    pub fn end_update(&mut self, now: f64) {
+        self.current.update_ms = now - self.update_start;
     }
 
-    pub fn end_draw(&mut self) {
-        self.draw_end = web_sys::window().unwrap().performance().unwrap().now();
+    pub fn start_draw(&mut self, now: f64) {
+        self.draw_start = now;
+    }
+
+    pub fn end_draw(&mut self, now: f64) {
+        self.current.draw_ms = now - self.draw_start;
     }
 
     pub fn add_draw_calls(&mut self, draw_calls: usize) {
-        self.draw_calls += draw_calls;
+        self.current.draw_calls += draw_calls;
     }
 
     pub fn add_sprites_drawn(&mut self, sprites_drawn: usize) {
-        self.sprites_drawn += sprites_drawn;
+        self.current.sprites_drawn += sprites_drawn;
     }
 
     pub fn add_sprites_skipped(&mut self, sprites_skipped: usize) {
-        self.sprites_skipped += sprites_skipped;
+        self.current.sprites_skipped += sprites_skipped;
+    }
+
+    pub fn add_buffer_bytes(&mut self, buffer_bytes: usize) {
+        self.current.buffer_bytes += buffer_bytes;
+    }
+
+    pub fn summary(&self) -> MetricsSummary {
+        if self.history.is_empty() {
+            return MetricsSummary::default();
+        }
+
+        let frames = self.history.len();
+        let frame = TimingSummary::from_samples(self.history.iter().map(|stats| stats.frame_ms).collect());
+
+        MetricsSummary {
+            frames,
+            frame,
+            update: TimingSummary::from_samples(self.history.iter().map(|stats| stats.update_ms).collect()),
+            draw: TimingSummary::from_samples(self.history.iter().map(|stats| stats.draw_ms).collect()),
+            fps: if frame.average > 0.0 { 1000.0 / frame.average } else { 0.0 },
+            average_draw_calls: self.history.iter().map(|stats| stats.draw_calls).sum::<usize>() as f64 / frames as f64,
+            average_sprites_drawn: self.history.iter().map(|stats| stats.sprites_drawn).sum::<usize>() as f64 / frames as f64,
+        }
     }
 
-    // TODO rust-analyzer gets confused
-    #[allow(unused_unsafe)]
     pub fn debug_print(&mut self) {
+        if !self.logging {
+            return;
+        }
+
         self.ticks += 1;
 
-        if self.ticks >= 99 {
+        if self.ticks >= WINDOW_SIZE {
             self.ticks = 0;
 
-            unsafe {
-                web_sys::console::log_1(&format!("update time: {:.2}ms", self.update_end - self.update_start).into());
-                web_sys::console::log_1(&format!("draw time: {:.2}ms", self.draw_end - self.draw_start).into());
-                web_sys::console::log_1(&format!("sprites drawn: {}, draw calls: {}, skipped: {}", self.sprites_drawn, self.draw_calls, self.sprites_skipped).into());
-            }
+            let summary = self.summary();
+            log::info!("fps: {:.1}, frame time: {:.2}ms (p95 {:.2}ms)", summary.fps, summary.frame.average, summary.frame.p95);
+            log::info!("update time: {:.2}ms, draw time: {:.2}ms", summary.update.average, summary.draw.average);
+            log::info!("sprites drawn: {}, draw calls: {}, skipped: {}", self.last.sprites_drawn, self.last.draw_calls, self.last.sprites_skipped);
+        }
+    }
+
+    /// draws the overlay if it is enabled
+    pub(crate) fn draw_overlay(context: &mut Context) -> Result<(), Error> {
+        if !context.metrics.overlay {
+            return Ok(());
+        }
+
+        let shapes = context.metrics.overlay_shapes();
+        shapes.draw(context)
+    }
+
+    fn overlay_shapes(&self) -> ShapeBatch {
+        const LEFT: i32 = 8;
+        const TOP: i32 = CANVAS_HEIGHT as i32 - 8;
+        const WIDTH: i32 = 2 * WINDOW_SIZE as i32;
+        const GRAPH_HEIGHT: i32 = 64;
+        // frame times in milliseconds that fill the graph
+        const GRAPH_SCALE: f64 = 50.0;
+
+        let stats = self.last;
+        let summary = self.summary();
+        let text = format!(
+            "fps {:.0}\nframe {:.1}ms p95 {:.1}ms\nupdate {:.2}ms draw {:.2}ms\ndraw calls {} sprites {} skipped {}\ntextures {} buffers {}kb",
+            summary.fps,
+            summary.frame.average,
+            summary.frame.p95,
+            stats.update_ms,
+            stats.draw_ms,
+            stats.draw_calls,
+            stats.sprites_drawn,
+            stats.sprites_skipped,
+            stats.texture_count,
+            stats.buffer_bytes / 1024,
+        );
+
+        let (_, text_height) = crate::graphics::debug_font::measure(&text);
+        let text_height = text_height * 2;
+        let height = text_height + GRAPH_HEIGHT + 24;
+
+        let mut shapes = ShapeBatch::new();
+        shapes.add_rectangle(&Rectangle::new(LEFT - 4, TOP - height, WIDTH + 8, height + 4), Color::new(0, 0, 0, 180));
+        shapes.add_debug_text(Point::new(LEFT, TOP - 4), &text, 2, Color::rgb(255, 255, 255));
+
+        let graph_bottom = TOP - height + 8;
+        for (index, frame) in self.history.iter().enumerate() {
+            let bar_height = ((frame.frame_ms / GRAPH_SCALE * GRAPH_HEIGHT as f64) as i32).clamp(1, GRAPH_HEIGHT);
+            let color = if frame.frame_ms > 33.4 {
+                Color::rgb(230, 60, 60)
+            } else if frame.frame_ms > 16.7 {
+                Color::rgb(230, 200, 60)
+            } else {
+                Color::rgb(80, 200, 80)
+            };
+
+            shapes.add_rectangle(&Rectangle::new(LEFT + index as i32 * 2, graph_bottom, 2, bar_height), color);
         }
 
+        // marks the time budget of a 60 fps frame
+        let budget = graph_bottom + (16.7 / GRAPH_SCALE * GRAPH_HEIGHT as f64) as i32;
+        shapes.add_line(Point::new(LEFT, budget), Point::new(LEFT + WIDTH, budget), 1, Color::new(255, 255, 255, 128));
+
+        shapes
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(metrics: &mut Metrics, frame_ms: f64, draw_calls: usize) {
+        metrics.begin_frame(frame_ms);
+        metrics.start_update(0.0);
+        metrics.end_update(1.0);
+        metrics.add_draw_calls(draw_calls);
+        metrics.end_frame(3);
+    }
+
+    #[test]
+    fn calculates_percentiles() {
+        let samples: Vec<f64> = (1..=100).map(|sample| sample as f64).collect();
+        assert_eq!(percentile(&samples, 0.5), 50.0);
+        assert_eq!(percentile(&samples, 0.95), 95.0);
+        assert_eq!(percentile(&samples, 1.0), 100.0);
+        assert_eq!(percentile(&samples, 0.0), 1.0);
+    }
+
+    #[test]
+    fn summarizes_window() {
+        let mut metrics = Metrics::new();
+        for index in 0..10 {
+            frame(&mut metrics, if index == 9 { 40.0 } else { 10.0 }, 2);
+        }
+
+        let summary = metrics.summary();
+        assert_eq!(summary.frames, 10);
+        assert_eq!(summary.frame.average, 13.0);
+        assert_eq!(summary.frame.median, 10.0);
+        assert_eq!(summary.frame.max, 40.0);
+        assert_eq!(summary.update.average, 1.0);
+        assert_eq!(summary.average_draw_calls, 2.0);
+        assert_eq!(metrics.last.texture_count, 3);
+        assert_eq!(metrics.last.fps(), 25.0);
+    }
+
+    #[test]
+    fn keeps_a_rolling_window() {
+        let mut metrics = Metrics::new();
+        for _ in 0..WINDOW_SIZE {
+            frame(&mut metrics, 100.0, 0);
+        }
+        for _ in 0..WINDOW_SIZE {
+            frame(&mut metrics, 10.0, 0);
+        }
+
+        let summary = metrics.summary();
+        assert_eq!(summary.frames, WINDOW_SIZE);
+        assert_eq!(summary.frame.max, 10.0);
+    }
+
+    #[test]
+    fn counts_reset_per_frame() {
+        let mut metrics = Metrics::new();
+        frame(&mut metrics, 16.0, 5);
+        metrics.begin_frame(16.0);
+        metrics.add_buffer_bytes(64);
+
+        assert_eq!(metrics.last.draw_calls, 5);
+        assert_eq!(metrics.current.draw_calls, 0);
+        assert_eq!(metrics.current.buffer_bytes, 64);
     }
 }
@@ -0,0 +1,350 @@
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// number of frames kept for export
+const DEFAULT_MAX_FRAMES: usize = 300;
+
+/// measures the time until the end of the enclosing block as a named profiling scope, e.g. `profile_scope!("ai");`
+#[macro_export]
+macro_rules! profile_scope {
+    ($name:expr) => {
+        let _profile_scope = $crate::ProfileScope::new($name);
+    };
+}
+
+/// turns collecting profiling scopes on or off
+pub fn set_profiling(enabled: bool) {
+    PROFILER.with(|profiler| profiler.borrow_mut().enabled = enabled);
+}
+
+pub fn is_profiling() -> bool {
+    PROFILER.with(|profiler| profiler.borrow().enabled)
+}
+
+/// replaces the clock used to time profiling scopes
+pub fn set_profiler_clock(clock: Box<dyn Clock>) {
+    PROFILER.with(|profiler| profiler.borrow_mut().clock = clock);
+}
+
+/// returns the recorded frames as Chrome Trace Event json, to be loaded in `about:tracing` or Perfetto
+pub fn export_chrome_trace() -> String {
+    PROFILER.with(|profiler| profiler.borrow().to_chrome_trace())
+}
+
+/// gives access to the profiler of the current thread
+pub fn with_profiler<R>(callback: impl FnOnce(&mut Profiler) -> R) -> R {
+    PROFILER.with(|profiler| callback(&mut profiler.borrow_mut()))
+}
+
+thread_local! {
+    static PROFILER: RefCell<Profiler> = RefCell::new(Profiler::new(default_clock()));
+}
+
+/// a source of timestamps in milliseconds
+pub trait Clock {
+    fn now(&self) -> f64;
+}
+
+/// reads time from `window.performance`
+pub struct PerformanceClock;
+
+impl Clock for PerformanceClock {
+    fn now(&self) -> f64 {
+        web_sys::window()
+            .and_then(|window| window.performance())
+            .map(|performance| performance.now())
+            .unwrap_or(0.0)
+    }
+}
+
+/// a clock that only moves when told to, clones share the same time
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    time: Rc<Cell<f64>>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// moves the clock forward by the given amount of milliseconds
+    pub fn advance(&self, milliseconds: f64) {
+        self.time.set(self.time.get() + milliseconds);
+    }
+
+    pub fn set(&self, milliseconds: f64) {
+        self.time.set(milliseconds);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> f64 {
+        self.time.get()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn default_clock() -> Box<dyn Clock> {
+    Box::new(PerformanceClock)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn default_clock() -> Box<dyn Clock> {
+    struct InstantClock(std::time::Instant);
+
+    impl Clock for InstantClock {
+        fn now(&self) -> f64 {
+            self.0.elapsed().as_secs_f64() * 1000.0
+        }
+    }
+
+    Box::new(InstantClock(std::time::Instant::now()))
+}
+
+/// a completed profiling scope
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileEvent {
+    pub name: Cow<'static, str>,
+    /// start of the scope in milliseconds
+    pub start: f64,
+    /// length of the scope in milliseconds
+    pub duration: f64,
+    /// number of scopes this one is nested in
+    pub depth: usize,
+}
+
+/// all scopes completed during one frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileFrame {
+    pub index: u64,
+    pub events: Vec<ProfileEvent>,
+}
+
+/// closes its profiling scope when dropped, created by `profile_scope!`
+pub struct ProfileScope {
+    active: bool,
+}
+
+impl ProfileScope {
+    pub fn new(name: impl Into<Cow<'static, str>>) -> Self {
+        let active = PROFILER.with(|profiler| profiler.borrow_mut().begin_scope(name));
+        Self {
+            active,
+        }
+    }
+}
+
+impl Drop for ProfileScope {
+    fn drop(&mut self) {
+        if self.active {
+            PROFILER.with(|profiler| profiler.borrow_mut().end_scope());
+        }
+    }
+}
+
+/// collects nested, named scopes per frame
+pub struct Profiler {
+    clock: Box<dyn Clock>,
+    enabled: bool,
+    frame: u64,
+    open: Vec<(Cow<'static, str>, f64)>,
+    current: Vec<ProfileEvent>,
+    frames: VecDeque<ProfileFrame>,
+    max_frames: usize,
+}
+
+impl Profiler {
+    pub fn new(clock: Box<dyn Clock>) -> Self {
+        Self {
+            clock,
+            enabled: false,
+            frame: 0,
+            open: Vec::new(),
+            current: Vec::new(),
+            frames: VecDeque::new(),
+            max_frames: DEFAULT_MAX_FRAMES,
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// sets how many of the most recent frames are kept
+    pub fn set_max_frames(&mut self, max_frames: usize) {
+        self.max_frames = max_frames;
+        while self.frames.len() > max_frames {
+            self.frames.pop_front();
+        }
+    }
+
+    /// opens a scope, returns false if profiling is disabled and nothing was recorded
+    pub fn begin_scope(&mut self, name: impl Into<Cow<'static, str>>) -> bool {
+        if !self.enabled {
+            return false;
+        }
+
+        self.open.push((name.into(), self.clock.now()));
+        true
+    }
+
+    /// closes the innermost open scope
+    pub fn end_scope(&mut self) {
+        if let Some((name, start)) = self.open.pop() {
+            self.current.push(ProfileEvent {
+                name,
+                start,
+                duration: self.clock.now() - start,
+                depth: self.open.len(),
+            });
+        }
+    }
+
+    /// stores the scopes completed since the last call as a frame
+    pub fn end_frame(&mut self) {
+        if !self.enabled && self.current.is_empty() {
+            return;
+        }
+
+        let events = std::mem::take(&mut self.current);
+        self.frames.push_back(ProfileFrame {
+            index: self.frame,
+            events,
+        });
+        self.frame += 1;
+
+        while self.frames.len() > self.max_frames {
+            self.frames.pop_front();
+        }
+    }
+
+    pub fn frames(&self) -> impl Iterator<Item = &ProfileFrame> {
+        self.frames.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.open.clear();
+        self.current.clear();
+        self.frames.clear();
+    }
+
+    /// serializes the recorded frames as Chrome Trace Event json
+    pub fn to_chrome_trace(&self) -> String {
+        let events: Vec<serde_json::Value> = self.frames.iter()
+            .flat_map(|frame| frame.events.iter().map(move |event| (frame.index, event)))
+            .map(|(frame, event)| serde_json::json!({
+                "name": event.name,
+                "cat": "niko",
+                "ph": "X",
+                "ts": event.start * 1000.0,
+                "dur": event.duration * 1000.0,
+                "pid": 1,
+                "tid": 1,
+                "args": { "frame": frame },
+            }))
+            .collect();
+
+        serde_json::json!({
+            "traceEvents": events,
+            "displayTimeUnit": "ms",
+        }).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profiler() -> (Profiler, ManualClock) {
+        let clock = ManualClock::new();
+        let mut profiler = Profiler::new(Box::new(clock.clone()));
+        profiler.set_enabled(true);
+        (profiler, clock)
+    }
+
+    #[test]
+    fn records_nested_scopes() {
+        let (mut profiler, clock) = profiler();
+
+        profiler.begin_scope("update");
+        clock.advance(1.0);
+        profiler.begin_scope("ai");
+        clock.advance(2.0);
+        profiler.end_scope();
+        clock.advance(0.5);
+        profiler.end_scope();
+        profiler.end_frame();
+
+        let frame = profiler.frames().next().unwrap();
+        assert_eq!(frame.events, vec![
+            ProfileEvent { name: "ai".into(), start: 1.0, duration: 2.0, depth: 1 },
+            ProfileEvent { name: "update".into(), start: 0.0, duration: 3.5, depth: 0 },
+        ]);
+    }
+
+    #[test]
+    fn ignores_scopes_while_disabled() {
+        let (mut profiler, _) = profiler();
+        profiler.set_enabled(false);
+
+        assert!(!profiler.begin_scope("update"));
+        profiler.end_scope();
+        profiler.end_frame();
+
+        assert_eq!(profiler.frames().count(), 0);
+    }
+
+    #[test]
+    fn keeps_recent_frames() {
+        let (mut profiler, _) = profiler();
+        profiler.set_max_frames(2);
+        for _ in 0..5 {
+            profiler.begin_scope("frame");
+            profiler.end_scope();
+            profiler.end_frame();
+        }
+
+        let indices: Vec<u64> = profiler.frames().map(|frame| frame.index).collect();
+        assert_eq!(indices, vec![3, 4]);
+    }
+
+    #[test]
+    fn exports_chrome_trace() {
+        let (mut profiler, clock) = profiler();
+        clock.set(10.0);
+        profiler.begin_scope(format!("entity {}", 7));
+        clock.advance(0.25);
+        profiler.end_scope();
+        profiler.end_frame();
+
+        let trace: serde_json::Value = serde_json::from_str(&profiler.to_chrome_trace()).unwrap();
+        let event = &trace["traceEvents"][0];
+        assert_eq!(event["name"], "entity 7");
+        assert_eq!(event["ph"], "X");
+        assert_eq!(event["ts"], 10000.0);
+        assert_eq!(event["dur"], 250.0);
+        assert_eq!(event["args"]["frame"], 0);
+    }
+
+    #[test]
+    fn scope_guard_uses_thread_profiler() {
+        let clock = ManualClock::new();
+        set_profiler_clock(Box::new(clock.clone()));
+        set_profiling(true);
+
+        {
+            profile_scope!("outer");
+            clock.advance(4.0);
+        }
+        with_profiler(|profiler| profiler.end_frame());
+
+        let names: Vec<String> = with_profiler(|profiler| {
+            profiler.frames().flat_map(|frame| frame.events.iter().map(|event| event.name.to_string())).collect()
+        });
+        assert_eq!(names, vec!["outer".to_string()]);
+        assert!(export_chrome_trace().contains("\"dur\":4000.0"));
+    }
+}
@@ -1,18 +1,255 @@
-use js_sys::Math;
+use std::cell::RefCell;
+use crate::Context;
 
-#[allow(unused_unsafe)]
+thread_local! {
+    static GLOBAL_RNG: RefCell<Rng> = RefCell::new(Rng::from_entropy());
+}
+
+/// returns a random number between 0 and 1, use `rng(context)` for numbers that can be replayed
 pub fn random() -> f64 {
-    unsafe {
-        Math::random()
-    }
+    GLOBAL_RNG.with(|rng| rng.borrow_mut().next_f64())
 }
 
-#[allow(unused_unsafe)]
+/// returns a random number from `from` up to but not including `to`
 pub fn random_range_i32(from: i32, to: i32) -> i32 {
-    let factor = unsafe { Math::random() };
+    GLOBAL_RNG.with(|rng| rng.borrow_mut().range(from, to))
+}
+
+/// the random number generator of the game, seeded by `Config::seed`
+pub fn rng(context: &mut Context) -> &mut Rng {
+    &mut context.rng
+}
+
+/// restarts the random number generator of the game, e.g. to replay a recorded run
+pub fn seed_rng(context: &mut Context, seed: u64) {
+    context.rng = Rng::new(seed);
+}
+
+/// a seedable xoshiro256** generator, the same seed always produces the same numbers on every platform
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rng {
+    state: [u64; 4],
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // splitmix64 spreads the seed over the whole state, which must not be all zeros
+        let mut seed = seed;
+        let mut next = || {
+            seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut value = seed;
+            value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            value ^ (value >> 31)
+        };
+
+        Self {
+            state: [next(), next(), next(), next()],
+        }
+    }
+
+    /// seeds the generator differently on every start
+    pub fn from_entropy() -> Self {
+        Self::new(entropy())
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let result = self.state[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let shifted = self.state[1] << 17;
+
+        self.state[2] ^= self.state[0];
+        self.state[3] ^= self.state[1];
+        self.state[1] ^= self.state[2];
+        self.state[0] ^= self.state[3];
+        self.state[2] ^= shifted;
+        self.state[3] = self.state[3].rotate_left(45);
+
+        result
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// returns a number from 0 up to but not including 1
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    /// returns a number from 0 up to but not including 1
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// returns a number from `from` up to but not including `to`, every number is equally likely
+    pub fn range(&mut self, from: i32, to: i32) -> i32 {
+        if to <= from {
+            return from;
+        }
+
+        let span = (to as i64 - from as i64) as u64;
+        // multiplying maps a 32 bit number onto the span, the rare values that would favor some results are rejected
+        let threshold = (1u64 << 32) % span;
+        loop {
+            let product = self.next_u32() as u64 * span;
+            if (product & 0xffff_ffff) >= threshold {
+                return (from as i64 + (product >> 32) as i64) as i32;
+            }
+        }
+    }
+
+    /// returns a number from `from` up to but not including `to`
+    pub fn range_f32(&mut self, from: f32, to: f32) -> f32 {
+        from + self.next_f32() * (to - from)
+    }
+
+    /// returns true with the given probability between 0 and 1
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+
+    /// returns a random item, `None` if there are none
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            return None;
+        }
+
+        items.get(self.index(items.len()))
+    }
+
+    /// returns a random item where items with a higher weight are more likely, `None` if no weight is above zero
+    pub fn choose_weighted<'a, T>(&mut self, items: &'a [T], weight: impl Fn(&T) -> f32) -> Option<&'a T> {
+        let total: f32 = items.iter().map(|item| weight(item).max(0.0)).sum();
+        if total <= 0.0 {
+            return None;
+        }
+
+        let mut remaining = self.next_f32() * total;
+        let mut last = None;
+        for item in items {
+            let weight = weight(item).max(0.0);
+            if weight <= 0.0 {
+                continue;
+            }
+            if remaining < weight {
+                return Some(item);
+            }
+            remaining -= weight;
+            last = Some(item);
+        }
+
+        // rounding can leave a tiny remainder after the last item
+        last
+    }
 
-    let range = to - from;
-    let number = factor * (range as f64);
+    /// puts the items into a random order
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for index in (1..items.len()).rev() {
+            let other = self.index(index + 1);
+            items.swap(index, other);
+        }
+    }
+
+    /// returns an index from 0 up to but not including `length`
+    fn index(&mut self, length: usize) -> usize {
+        if length <= i32::MAX as usize {
+            self.range(0, length as i32) as usize
+        } else {
+            (self.next_u64() % length as u64) as usize
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn entropy() -> u64 {
+    let random = (js_sys::Math::random() * (1u64 << 53) as f64) as u64;
+    random ^ (js_sys::Date::now() as u64).rotate_left(32)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn entropy() -> u64 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or(0);
+    let local = 0u8;
+    nanos ^ (&local as *const u8 as u64).rotate_left(32)
+}
 
-    from + (number as i32)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_numbers() {
+        let mut first = Rng::new(42);
+        let mut second = Rng::new(42);
+        let numbers: Vec<u64> = (0..8).map(|_| first.next_u64()).collect();
+        assert_eq!(numbers, (0..8).map(|_| second.next_u64()).collect::<Vec<u64>>());
+
+        assert_ne!(Rng::new(43).next_u64(), numbers[0]);
+        assert_ne!(Rng::new(0).next_u64(), 0);
+    }
+
+    #[test]
+    fn ranges_stay_in_bounds_and_are_fair() {
+        let mut rng = Rng::new(7);
+        let mut counts = [0; 3];
+        for _ in 0..30_000 {
+            let value = rng.range(-2, 1);
+            assert!((-2..1).contains(&value));
+            counts[(value + 2) as usize] += 1;
+        }
+        // each value is expected 10000 times
+        assert!(counts.iter().all(|count| (9_500..10_500).contains(count)), "{:?}", counts);
+
+        assert_eq!(rng.range(5, 5), 5);
+        assert_eq!(rng.range(5, -5), 5);
+        for _ in 0..100 {
+            rng.range(i32::MIN, i32::MAX);
+            let value = rng.range_f32(-1.0, 1.0);
+            assert!((-1.0..1.0).contains(&value));
+        }
+    }
+
+    #[test]
+    fn free_functions_work_off_wasm() {
+        for _ in 0..100 {
+            assert!((0.0..1.0).contains(&random()));
+            assert!((-3..3).contains(&random_range_i32(-3, 3)));
+        }
+    }
+
+    #[test]
+    fn chooses_and_shuffles() {
+        let mut rng = Rng::new(1);
+        assert!(!rng.chance(0.0));
+        assert!(rng.chance(1.0));
+        assert_eq!(rng.choose::<u8>(&[]), None);
+        assert_eq!(rng.choose(&[3]), Some(&3));
+
+        let mut items: Vec<u32> = (0..20).collect();
+        rng.shuffle(&mut items);
+        assert_ne!(items, (0..20).collect::<Vec<u32>>());
+        items.sort_unstable();
+        assert_eq!(items, (0..20).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn weighted_choice_follows_the_weights() {
+        let mut rng = Rng::new(3);
+        let items = [("never", 0.0), ("rare", 1.0), ("common", 3.0)];
+
+        let mut common = 0;
+        for _ in 0..4_000 {
+            let (name, _) = rng.choose_weighted(&items, |(_, weight)| *weight).unwrap();
+            assert_ne!(*name, "never");
+            if *name == "common" {
+                common += 1;
+            }
+        }
+        assert!((2_800..3_200).contains(&common), "{}", common);
+
+        assert_eq!(rng.choose_weighted(&items[..1], |(_, weight)| *weight), None);
+    }
 }
@@ -1,6 +1,6 @@
 use crate::Point;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Rectangle {
     pub x: i32,
     pub y: i32,
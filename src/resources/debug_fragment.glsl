@@ -1,8 +1,7 @@
 precision highp float;
-
-uniform vec4 color;
+varying vec4 vertex_color;
 
 void main()
 {
-    gl_FragColor = color;
+    gl_FragColor = vertex_color;
 }
@@ -1,6 +1,10 @@
 precision highp float;
-attribute vec4 position;
+attribute vec2 position;
+attribute vec4 color;
+
+varying vec4 vertex_color;
 
 void main() {
-    gl_Position = position;
+    vertex_color = color;
+    gl_Position = vec4(position.x, position.y, 0.0, 1.0);
 }
@@ -1,7 +1,66 @@
-pub const SPRITE_BATCH_VERTEX: &'static str = include_str!("./sprite_batch_vertex.glsl");
-pub const SPRITE_BATCH_FRAGMENT: &'static str = include_str!("./sprite_batch_fragment.glsl");
+pub const SPRITE_BATCH_VERTEX: &str = include_str!("./sprite_batch_vertex.glsl");
+pub const SPRITE_BATCH_FRAGMENT: &str = include_str!("./sprite_batch_fragment.glsl");
 
-#[allow(dead_code)]
-pub const DEBUG_VERTEX: &'static str = include_str!("./debug_vertex.glsl");
-#[allow(dead_code)]
-pub const DEBUG_FRAGMENT: &'static str = include_str!("./debug_fragment.glsl");
+pub const DEBUG_VERTEX: &str = include_str!("./debug_vertex.glsl");
+pub const DEBUG_FRAGMENT: &str = include_str!("./debug_fragment.glsl");
+
+/// the engine shaders, each built from a vertex and a fragment source
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum EngineShader {
+    SpriteBatch,
+    Debug,
+}
+
+/// the sources of the engine shaders, replaced when they are hot reloaded during development
+#[derive(Debug, Clone)]
+pub(crate) struct ShaderSources {
+    pub(crate) sprite_batch_vertex: String,
+    pub(crate) sprite_batch_fragment: String,
+    pub(crate) debug_vertex: String,
+    pub(crate) debug_fragment: String,
+}
+
+impl ShaderSources {
+    /// replaces a source by its file name, e.g. `src/resources/debug_vertex.glsl`, and returns the shader using it
+    pub(crate) fn replace(&mut self, name: &str, source: String) -> Option<EngineShader> {
+        let (slot, shader) = match name.rsplit(['/', '\\']).next().unwrap_or(name) {
+            "sprite_batch_vertex.glsl" => (&mut self.sprite_batch_vertex, EngineShader::SpriteBatch),
+            "sprite_batch_fragment.glsl" => (&mut self.sprite_batch_fragment, EngineShader::SpriteBatch),
+            "debug_vertex.glsl" => (&mut self.debug_vertex, EngineShader::Debug),
+            "debug_fragment.glsl" => (&mut self.debug_fragment, EngineShader::Debug),
+            _ => return None,
+        };
+
+        *slot = source;
+        Some(shader)
+    }
+}
+
+impl Default for ShaderSources {
+    fn default() -> Self {
+        Self {
+            sprite_batch_vertex: SPRITE_BATCH_VERTEX.to_string(),
+            sprite_batch_fragment: SPRITE_BATCH_FRAGMENT.to_string(),
+            debug_vertex: DEBUG_VERTEX.to_string(),
+            debug_fragment: DEBUG_FRAGMENT.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_sources_by_file_name() {
+        let mut sources = ShaderSources::default();
+
+        assert_eq!(sources.replace("src/resources/debug_fragment.glsl", "new".to_string()), Some(EngineShader::Debug));
+        assert_eq!(sources.debug_fragment, "new");
+        assert_eq!(sources.replace("sprite_batch_vertex.glsl", "vertex".to_string()), Some(EngineShader::SpriteBatch));
+        assert_eq!(sources.sprite_batch_vertex, "vertex");
+
+        assert_eq!(sources.replace("shaders/water.glsl", String::new()), None);
+        assert_eq!(sources.sprite_batch_fragment, SPRITE_BATCH_FRAGMENT);
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use wasm_bindgen::JsCast;
+use crate::{Context, Error, NikoError};
+
+/// stores data under a key, it replaces what was saved under the key before
+pub fn save<T: SaveData>(context: &mut Context, key: &str, data: &T) -> Result<(), Error> {
+    save_to(context.storage.as_mut(), key, data)
+}
+
+/// loads data saved under a key, `None` if nothing was saved, data of older versions is migrated
+pub fn load<T: SaveData>(context: &Context, key: &str) -> Result<Option<T>, Error> {
+    load_from(context.storage.as_ref(), key)
+}
+
+pub fn delete_save(context: &mut Context, key: &str) -> Result<(), Error> {
+    context.storage.remove(key)?;
+    Ok(())
+}
+
+/// replaces where saves are kept, e.g. with a `MemoryStorage` in tests or a prefixed `LocalStorage`
+pub fn set_storage(context: &mut Context, storage: Box<dyn StorageBackend>) {
+    context.storage = storage;
+}
+
+/// data that can be saved, the version is stored with it so saves of older versions can be migrated
+pub trait SaveData: Serialize + DeserializeOwned {
+    const VERSION: u32 = 1;
+
+    /// turns data saved by an older version into data of the current version, the default refuses it
+    fn migrate(version: u32, _data: serde_json::Value) -> Result<serde_json::Value, Error> {
+        Err(NikoError::InvalidArgument(format!("no migration from version {} to {}", version, Self::VERSION)).into())
+    }
+}
+
+/// how saves are stored, `{ "version": 1, "data": ... }` as json
+#[derive(Serialize, Deserialize)]
+struct Envelope<T> {
+    version: u32,
+    data: T,
+}
+
+fn save_to<T: SaveData>(storage: &mut dyn StorageBackend, key: &str, data: &T) -> Result<(), Error> {
+    let text = serde_json::to_string(&Envelope { version: T::VERSION, data })?;
+    storage.set(key, &text)?;
+    Ok(())
+}
+
+fn load_from<T: SaveData>(storage: &dyn StorageBackend, key: &str) -> Result<Option<T>, Error> {
+    let text = match storage.get(key)? {
+        Some(text) => text,
+        None => return Ok(None),
+    };
+
+    let decode_error = |reason: String| NikoError::DecodeError { asset: format!("save data {}", key), reason };
+    let envelope: Envelope<serde_json::Value> = serde_json::from_str(&text)
+        .map_err(|error| decode_error(error.to_string()))?;
+
+    let Envelope { version, data } = envelope;
+    let data = if version == T::VERSION {
+        data
+    } else if version < T::VERSION {
+        T::migrate(version, data)
+            .map_err(|error| error.context(decode_error(format!("could not migrate version {}", version))))?
+    } else {
+        return Err(decode_error(format!("version {} is newer than the supported version {}", version, T::VERSION)).into());
+    };
+
+    let data = serde_json::from_value(data)
+        .map_err(|error| decode_error(error.to_string()))?;
+    Ok(Some(data))
+}
+
+/// a key value store for saves
+pub trait StorageBackend {
+    fn get(&self, key: &str) -> Result<Option<String>, NikoError>;
+    /// fails with `NikoError::QuotaExceeded` if the value does not fit
+    fn set(&mut self, key: &str, value: &str) -> Result<(), NikoError>;
+    fn remove(&mut self, key: &str) -> Result<(), NikoError>;
+}
+
+/// keeps saves in `window.localStorage`
+#[derive(Debug, Clone, Default)]
+pub struct LocalStorage {
+    prefix: String,
+}
+
+impl LocalStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// puts a prefix in front of every key, e.g. to keep games on the same domain apart
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    fn storage(&self) -> Result<web_sys::Storage, NikoError> {
+        web_sys::window()
+            .ok_or_else(|| NikoError::StorageUnavailable("no window found".to_string()))?
+            .local_storage()
+            .map_err(|_| NikoError::StorageUnavailable("localStorage was denied".to_string()))?
+            .ok_or_else(|| NikoError::StorageUnavailable("localStorage is missing".to_string()))
+    }
+}
+
+impl StorageBackend for LocalStorage {
+    fn get(&self, key: &str) -> Result<Option<String>, NikoError> {
+        self.storage()?
+            .get_item(&format!("{}{}", self.prefix, key))
+            .map_err(|_| NikoError::StorageUnavailable(format!("could not read {}", key)))
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), NikoError> {
+        self.storage()?
+            .set_item(&format!("{}{}", self.prefix, key), value)
+            .map_err(|error| {
+                let name = error.dyn_ref::<web_sys::DomException>().map(|exception| exception.name());
+                match name.as_deref() {
+                    // firefox used its own name for a long time
+                    Some("QuotaExceededError") | Some("NS_ERROR_DOM_QUOTA_REACHED") => NikoError::QuotaExceeded { key: key.to_string() },
+                    _ => NikoError::StorageUnavailable(format!("could not write {}", key)),
+                }
+            })
+    }
+
+    fn remove(&mut self, key: &str) -> Result<(), NikoError> {
+        self.storage()?
+            .remove_item(&format!("{}{}", self.prefix, key))
+            .map_err(|_| NikoError::StorageUnavailable(format!("could not remove {}", key)))
+    }
+}
+
+/// keeps saves in memory until the page is closed, e.g. for tests
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStorage {
+    values: HashMap<String, String>,
+    quota: Option<usize>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// limits the bytes of all keys and values together, like the quota of a browser
+    pub fn with_quota(mut self, bytes: usize) -> Self {
+        self.quota = Some(bytes);
+        self
+    }
+
+    fn used_bytes(&self) -> usize {
+        self.values.iter().map(|(key, value)| key.len() + value.len()).sum()
+    }
+}
+
+impl StorageBackend for MemoryStorage {
+    fn get(&self, key: &str) -> Result<Option<String>, NikoError> {
+        Ok(self.values.get(key).cloned())
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), NikoError> {
+        if let Some(quota) = self.quota {
+            let replaced = self.values.get(key).map_or(0, |old| key.len() + old.len());
+            if self.used_bytes() - replaced + key.len() + value.len() > quota {
+                return Err(NikoError::QuotaExceeded { key: key.to_string() });
+            }
+        }
+
+        self.values.insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &str) -> Result<(), NikoError> {
+        self.values.remove(key);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Settings {
+        volume: f32,
+    }
+
+    impl SaveData for Settings {}
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Progress {
+        level: u32,
+        coins: u32,
+    }
+
+    impl SaveData for Progress {
+        const VERSION: u32 = 3;
+
+        fn migrate(version: u32, mut data: serde_json::Value) -> Result<serde_json::Value, Error> {
+            if version < 2 {
+                data["coins"] = 0.into();
+            }
+            if version < 3 {
+                data["level"] = (data["stage"].as_u64().unwrap_or(0) + 1).into();
+            }
+            Ok(data)
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        let mut storage = MemoryStorage::new();
+        assert_eq!(load_from::<Settings>(&storage, "settings").unwrap(), None);
+
+        save_to(&mut storage, "settings", &Settings { volume: 0.5 }).unwrap();
+        assert_eq!(storage.get("settings").unwrap().unwrap(), r#"{"version":1,"data":{"volume":0.5}}"#);
+        assert_eq!(load_from::<Settings>(&storage, "settings").unwrap(), Some(Settings { volume: 0.5 }));
+
+        storage.remove("settings").unwrap();
+        assert_eq!(load_from::<Settings>(&storage, "settings").unwrap(), None);
+    }
+
+    #[test]
+    fn migrates_old_versions() {
+        let mut storage = MemoryStorage::new();
+        storage.set("progress", r#"{"version":1,"data":{"stage":4}}"#).unwrap();
+
+        assert_eq!(load_from::<Progress>(&storage, "progress").unwrap(), Some(Progress { level: 5, coins: 0 }));
+    }
+
+    #[test]
+    fn rejects_newer_versions_and_unmigrated_data() {
+        let mut storage = MemoryStorage::new();
+        storage.set("progress", r#"{"version":4,"data":{"level":1,"coins":2}}"#).unwrap();
+        storage.set("settings", r#"{"version":0,"data":{"volume":1.0}}"#).unwrap();
+        storage.set("broken", "{").unwrap();
+
+        assert!(load_from::<Progress>(&storage, "progress").is_err());
+        assert!(load_from::<Settings>(&storage, "settings").is_err());
+        assert!(load_from::<Settings>(&storage, "broken").is_err());
+    }
+
+    #[test]
+    fn reports_exceeded_quota() {
+        let mut storage = MemoryStorage::new().with_quota(64);
+        save_to(&mut storage, "settings", &Settings { volume: 1.0 }).unwrap();
+        // replacing a value only counts the new one
+        save_to(&mut storage, "settings", &Settings { volume: 0.25 }).unwrap();
+
+        let error = save_to(&mut storage, "progress", &Progress { level: 100, coins: 100 }).unwrap_err();
+        assert!(matches!(error.downcast_ref::<NikoError>(), Some(NikoError::QuotaExceeded { key }) if key == "progress"));
+        assert_eq!(load_from::<Settings>(&storage, "settings").unwrap(), Some(Settings { volume: 0.25 }));
+    }
+}
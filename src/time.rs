@@ -0,0 +1,84 @@
+use crate::Context;
+
+/// the longest frame delta in seconds, longer pauses e.g. from a background tab are cut to this
+pub const MAX_DELTA: f32 = 0.25;
+
+/// returns the time in seconds that passed between the previous frame and the current one, at most `MAX_DELTA`
+pub fn delta_time(context: &Context) -> f32 {
+    context.time.delta()
+}
+
+/// returns the time in seconds since the game loop started
+pub fn total_time(context: &Context) -> f64 {
+    context.time.total()
+}
+
+pub struct Time {
+    last_frame: Option<f64>,
+    delta: f32,
+    total: f64,
+}
+
+impl Time {
+    pub fn new() -> Self {
+        Self {
+            last_frame: None,
+            delta: 0.0,
+            total: 0.0,
+        }
+    }
+
+    /// advances the clock to `now`, given in milliseconds
+    pub(crate) fn tick(&mut self, now: f64) {
+        if let Some(last_frame) = self.last_frame {
+            let delta = ((now - last_frame) / 1000.0).clamp(0.0, MAX_DELTA as f64);
+            self.delta = delta as f32;
+            self.total += delta;
+        }
+
+        self.last_frame = Some(now);
+    }
+
+    pub fn delta(&self) -> f32 {
+        self.delta
+    }
+
+    pub fn total(&self) -> f64 {
+        self.total
+    }
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_the_time_between_ticks() {
+        let mut time = Time::new();
+        time.tick(1000.0);
+        assert_eq!(time.delta(), 0.0);
+
+        time.tick(1016.0);
+        assert!((time.delta() - 0.016).abs() < 1e-6);
+        assert!((time.total() - 0.016).abs() < 1e-9);
+    }
+
+    #[test]
+    fn clamps_long_pauses() {
+        let mut time = Time::new();
+        time.tick(0.0);
+        time.tick(60_000.0);
+        assert_eq!(time.delta(), MAX_DELTA);
+        assert_eq!(time.total(), MAX_DELTA as f64);
+
+        // a clock going backward never makes time run in reverse
+        time.tick(59_000.0);
+        assert_eq!(time.delta(), 0.0);
+    }
+}